@@ -1,18 +1,56 @@
-//! FIXME could be way more incremental: only recalculate envs for the
-//! transitive closure of the files that import this file, stopping if updated
-//! envs are the same. use `salsa` for that?
+//! The database of C0 files.
+//!
+//! The inputs to the database are the contents of the files: those of the
+//! editor's buffer for files open in an editor, else those on disk. Everything
+//! else is derived from the inputs by queries that are computed on demand and
+//! memoized, so e.g. asking for hover information in one file only checks that
+//! file and the files it `#use`s.
+//!
+//! - Lexing, parsing, and lowering a file depend only on its contents. When the
+//!   contents change, we either re-parse only the edited part of the file (see
+//!   `reparse`), or forget them.
+//! - Resolving the `#use`s of a file also depends on what files exist, so we
+//!   forget them when files are created or deleted.
+//! - The env of a file depends on all of the above for that file, and on the
+//!   envs of the files it `#use`s. After an edit, we re-verify the memoized env
+//!   the next time it is asked for: if none of its dependencies changed since it
+//!   was last verified, we reuse it. If we do re-check a file but it exports the
+//!   same items as before, we keep the revision at which its exports last
+//!   changed, so files that `#use` it need not be re-checked ("early cutoff").
+//! - The diagnostics of a file depend on its syntax and env, so we reuse them if
+//!   those are unchanged, and on the [`Config`], so we forget them when it
+//!   changes.
+//!
+//! A file may also be part of one or more targets (see [`Target`]), in which
+//! case it also sees the items of the files before it in each target. Its env
+//! and diagnostics then depend on the target, so we memoize them separately for
+//! each target the file is in. A file in no target is checked on its own.
 
-use crate::queries::{all_diagnostics, go_to_def, hover};
-use crate::types::{Diagnostic, Edit, Hover, Location, Update};
+use crate::position_db::PositionDb;
+use crate::queries::call_hierarchy::{self, CallSite};
+use crate::queries::{
+  all_diagnostics, completions, folding_ranges, go_to_def, hover, inspect,
+  memory_usage, selection_ranges, semantic_tokens,
+};
+use crate::reparse;
+use crate::types::{
+  Call, Completion, Config, Diagnostic, Edit, FnItem, FoldingRange, Hover,
+  Location, MemoryUsage, SemanticToken, Target, Update,
+};
 use lower::Ptrs;
-use rustc_hash::FxHashMap;
-use statics::{Cx, Env, FileId};
+use rustc_hash::{FxHashMap, FxHashSet};
+use statics::{Bodies, Cx, Env, FileId, TyDb};
+use std::cell::{Ref, RefCell};
+use std::collections::BTreeSet;
 use std::hash::BuildHasherDefault;
+use std::rc::Rc;
+use std_lib::StdLib;
 use syntax::ast::{AstNode, Root as AstRoot};
 use syntax::rowan::TextRange;
 use syntax::SyntaxNode;
-use text_pos::{Position, PositionDb};
+use text_pos::{Position, Range};
 use topo_sort::Graph;
+use unwrap_or::unwrap_or;
 use uri_db::{Uri, UriDb, UriId};
 use uses::UseKind;
 
@@ -20,9 +58,21 @@ use uses::UseKind;
 #[derive(Debug)]
 pub struct Db {
   pub(crate) uris: UriDb,
-  pub(crate) ordering: Vec<UriId>,
-  pub(crate) syntax_data: FxHashMap<UriId, SyntaxData>,
-  pub(crate) kind: DbKind,
+  pub(crate) std_lib: StdLib,
+  config: Config,
+  /// incremented on every change to the inputs.
+  revision: Revision,
+  /// the last revision in which files were created or deleted, which can change
+  /// what the `#use`s in any file resolve to.
+  uris_changed_at: Revision,
+  pub(crate) files: FxHashMap<UriId, File>,
+  pub(crate) targets: Vec<TargetData>,
+  ordering: RefCell<Option<Rc<Ordering>>>,
+  /// shared by all files and libraries, and kept across revisions, so each
+  /// type is only interned once.
+  pub(crate) cx: RefCell<Cx>,
+  /// the files whose envs we are currently computing, to detect cycles.
+  active: RefCell<Vec<(UriId, Scope)>>,
 }
 
 impl Db {
@@ -32,11 +82,22 @@ impl Db {
     I: IntoIterator<Item = (Uri, String)>,
   {
     let mut uris = UriDb::default();
-    let syntax_data: FxHashMap<_, _> = files
+    let files: FxHashMap<_, _> = files
       .into_iter()
-      .map(|(uri, contents)| (uris.insert(uri), get_syntax_data(contents)))
+      .map(|(uri, contents)| (uris.insert(uri), File::new(contents, 0)))
       .collect();
-    get_all_semantic_data(uris, syntax_data)
+    Self {
+      uris,
+      std_lib: StdLib::default(),
+      config: Config::default(),
+      revision: 0,
+      uris_changed_at: 0,
+      files,
+      targets: Vec::new(),
+      ordering: RefCell::default(),
+      cx: RefCell::default(),
+      active: RefCell::default(),
+    }
   }
 
   /// Edit a single file.
@@ -50,64 +111,221 @@ impl Db {
       Some(x) => x,
       None => return,
     };
-    let uris = std::mem::take(&mut self.uris);
-    let mut syntax_data = std::mem::take(&mut self.syntax_data);
-    let sd = syntax_data.remove(&id).expect("got URI but no syntax data");
-    let mut positions = Some(sd.positions);
-    let mut contents = sd.contents;
+    self.revision += 1;
+    *self.ordering.get_mut() = None;
+    let file = self.files.get_mut(&id).expect("got URI but no file");
+    // we try to keep the syntax data up to date by reparsing only what was
+    // edited. if we can't, we keep just the positions up to date, for
+    // converting the ranges of later edits.
+    let mut syntax = file
+      .syntax
+      .get_mut()
+      .take()
+      .and_then(|sd| Rc::try_unwrap(sd).ok());
+    let mut positions = None::<PositionDb>;
+    let contents = &mut file.contents;
     for edit in edits {
-      match edit.range {
-        None => contents = edit.text,
-        Some(range) => {
-          // FIXME could only invalidate `positions` based on the range of the
-          // edits
-          let text_range = positions
-            .unwrap_or_else(|| PositionDb::new(&contents))
-            .text_range(range);
-          if let Some(text_range) = text_range {
-            let range = std::ops::Range::<usize>::from(text_range);
-            contents.replace_range(range, &edit.text);
+      let range = match edit.range {
+        Some(x) => x,
+        None => {
+          *contents = edit.text;
+          syntax = None;
+          positions = None;
+          continue;
+        }
+      };
+      let text_range = match syntax.as_ref() {
+        Some(sd) => sd.positions.text_range(range),
+        None => positions
+          .get_or_insert_with(|| PositionDb::new(contents))
+          .text_range(range),
+      };
+      let text_range = unwrap_or!(text_range, continue);
+      contents
+        .replace_range(std::ops::Range::<usize>::from(text_range), &edit.text);
+      match syntax.take() {
+        Some(sd) => match reparse::get(sd, text_range, &edit.text) {
+          Ok(sd) => syntax = Some(sd),
+          Err(ps) => positions = Some(ps),
+        },
+        None => {
+          if let Some(ps) = positions.as_mut() {
+            ps.edit(text_range, &edit.text);
           }
         }
       }
-      positions = None;
     }
-    assert!(syntax_data.insert(id, get_syntax_data(contents)).is_none());
-    *self = get_all_semantic_data(uris, syntax_data)
+    *file.syntax.get_mut() = syntax.map(Rc::new);
+    file.changed_at = self.revision;
+    *file.uses.get_mut() = None;
   }
 
-  /// Update some files.
+  /// Update some files on disk.
   ///
-  /// The files may or may not be in the `Db`.
+  /// The files may or may not be in the `Db`. If a file is open, this only
+  /// changes what it will revert to when closed.
   pub fn update_files<I>(&mut self, updates: I)
   where
     I: IntoIterator<Item = Update>,
   {
-    let mut uris = std::mem::take(&mut self.uris);
-    let mut syntax_data = std::mem::take(&mut self.syntax_data);
+    self.revision += 1;
+    self.uris_changed_at = self.revision;
+    *self.ordering.get_mut() = None;
     for update in updates {
       match update {
         Update::Create(uri, contents) => {
-          let id = uris.insert(uri);
-          syntax_data.insert(id, get_syntax_data(contents));
+          let id = self.uris.insert(uri);
+          match self.files.get_mut(&id) {
+            Some(file) if file.open => file.disk = Some(contents),
+            _ => {
+              self.files.insert(id, File::new(contents, self.revision));
+            }
+          }
         }
-        Update::Delete(uri) => match uris.remove(&uri) {
-          Some(id) => {
-            syntax_data.remove(&id);
+        Update::Delete(uri) => {
+          let id = unwrap_or!(self.uris.get_id(&uri), continue);
+          match self.files.get_mut(&id) {
+            Some(file) if file.open => file.disk = None,
+            _ => {
+              self.uris.remove(&uri);
+              self.files.remove(&id);
+            }
           }
-          None => continue,
-        },
+        }
+      }
+    }
+    for file in self.files.values_mut() {
+      *file.uses.get_mut() = None;
+    }
+    self.resolve_targets();
+  }
+
+  /// Sets the targets, replacing any old ones.
+  ///
+  /// The files of a target need not be in the `Db`. Those that aren't are
+  /// skipped until they are created.
+  pub fn set_targets<I>(&mut self, targets: I)
+  where
+    I: IntoIterator<Item = Target>,
+  {
+    self.revision += 1;
+    self.targets = targets
+      .into_iter()
+      .map(|target| TargetData {
+        target,
+        files: Vec::new(),
+        memos: FxHashMap::default(),
+      })
+      .collect();
+    self.resolve_targets();
+  }
+
+  /// Sets the config, replacing the old one.
+  pub fn set_config(&mut self, config: Config) {
+    if self.config == config {
+      return;
+    }
+    self.revision += 1;
+    if self.config.include_dirs != config.include_dirs {
+      // like creating or deleting files, this can change what `#use`s resolve
+      // to.
+      self.uris_changed_at = self.revision;
+      *self.ordering.get_mut() = None;
+      for file in self.files.values_mut() {
+        *file.uses.get_mut() = None;
       }
     }
-    *self = get_all_semantic_data(uris, syntax_data)
+    let memos = self
+      .files
+      .values_mut()
+      .map(|f| &mut f.memos)
+      .chain(self.targets.iter_mut().flat_map(|t| t.memos.values_mut()));
+    for memos in memos {
+      *memos.diagnostics.get_mut() = None;
+    }
+    self.config = config;
+  }
+
+  /// Opens a file in an editor. Until it is closed, its contents are those of
+  /// the editor's buffer, which are kept up to date with `edit_file`, instead
+  /// of those on disk.
+  ///
+  /// The file may or may not be in the `Db`, e.g. it may be new and unsaved.
+  pub fn open_file(&mut self, uri: Uri, contents: String) {
+    let id = match self.uris.get_id(&uri) {
+      Some(x) => x,
+      None => {
+        self.update_files([Update::Create(uri.clone(), contents)]);
+        let id = self.uris.get_id(&uri).expect("just created");
+        let file = self.files.get_mut(&id).expect("got URI but no file");
+        file.open = true;
+        file.disk = None;
+        return;
+      }
+    };
+    let file = self.files.get_mut(&id).expect("got URI but no file");
+    if !file.open {
+      file.open = true;
+      file.disk = Some(file.contents.clone());
+    }
+    if file.contents != contents {
+      let edit = Edit {
+        text: contents,
+        range: None,
+      };
+      self.edit_file(&uri, [edit]);
+    }
+  }
+
+  /// Notes that an open file was saved, so its contents are now also those on
+  /// disk.
+  pub fn save_file(&mut self, uri: &Uri) {
+    let id = unwrap_or!(self.uris.get_id(uri), return);
+    let file = self.files.get_mut(&id).expect("got URI but no file");
+    if file.open {
+      file.disk = Some(file.contents.clone());
+    }
+  }
+
+  /// Closes an open file, so its contents are those on disk again. If it is
+  /// not on disk, it is removed.
+  pub fn close_file(&mut self, uri: &Uri) {
+    let id = unwrap_or!(self.uris.get_id(uri), return);
+    let file = self.files.get_mut(&id).expect("got URI but no file");
+    if !file.open {
+      return;
+    }
+    file.open = false;
+    match file.disk.take() {
+      Some(disk) => {
+        if file.contents != disk {
+          let edit = Edit {
+            text: disk,
+            range: None,
+          };
+          self.edit_file(uri, [edit]);
+        }
+      }
+      None => self.update_files([Update::Delete(uri.clone())]),
+    }
+  }
+
+  /// Returns the open files and the contents of their buffers.
+  pub fn open_files(&self) -> impl Iterator<Item = (&Uri, &str)> + '_ {
+    self
+      .files
+      .iter()
+      .filter(|(_, file)| file.open)
+      .map(|(&id, file)| (&self.uris[id], file.contents.as_str()))
   }
 
   /// Formats the file at the given URI.
   pub fn format(&self, uri: &Uri) -> Option<String> {
     let id = self.uris.get_id(uri)?;
-    let errors = &self.syntax_data[&id].errors;
+    let syntax_data = self.syntax_data(id);
+    let errors = &syntax_data.errors;
     if errors.lex.is_empty() && errors.parse.is_empty() {
-      fmt::get(self.syntax_data[&id].ast_root.clone())
+      fmt::get(syntax_data.ast_root.clone(), &self.config.format)
     } else {
       None
     }
@@ -116,14 +334,59 @@ impl Db {
   /// Returns the parse tree of the file at the given URI.
   pub fn syntax(&self, uri: &Uri) -> Option<SyntaxNode> {
     let id = self.uris.get_id(uri)?;
-    Some(self.syntax_data[&id].ast_root.syntax().clone())
+    Some(self.syntax_data(id).ast_root.syntax().clone())
+  }
+
+  /// Returns the syntax tree of the file, pretty-printed.
+  pub fn syntax_tree(&self, uri: &Uri) -> Option<String> {
+    inspect::syntax_tree(self, uri)
+  }
+
+  /// Returns the tokens of the file, one per line, with their kinds and
+  /// ranges.
+  pub fn tokens(&self, uri: &Uri) -> Option<String> {
+    inspect::tokens(self, uri)
+  }
+
+  /// Returns the HIR of the file, pretty-printed, with the type of each
+  /// expression and type.
+  pub fn hir(&self, uri: &Uri) -> Option<String> {
+    inspect::hir(self, uri)
   }
 
   /// Returns all diagnostics of every file.
   pub fn all_diagnostics(&self) -> Vec<(Uri, Vec<Diagnostic>)> {
+    self
+      .diagnostics_by_file()
+      .map(|(uri, ds, _)| (uri, ds))
+      .collect()
+  }
+
+  /// Returns all diagnostics of every file, along with their hashes (see
+  /// [`Db::file_diagnostics`]), computing the diagnostics of each file only when
+  /// the iterator gets to it.
+  pub fn diagnostics_by_file(
+    &self,
+  ) -> impl ExactSizeIterator<Item = (Uri, Vec<Diagnostic>, u64)> + '_ {
     all_diagnostics::get(self)
   }
 
+  /// Returns the diagnostics of the file, along with a hash of them.
+  ///
+  /// If the diagnostics of a file are unchanged, so is the hash. So a client can
+  /// be told that the diagnostics it has are still up to date without sending
+  /// them again.
+  pub fn file_diagnostics(&self, uri: &Uri) -> Option<(Vec<Diagnostic>, u64)> {
+    let id = self.uris.get_id(uri)?;
+    let (ds, hash) = all_diagnostics::get_one(self, &self.ordering(), id);
+    Some((ds.as_ref().clone(), hash))
+  }
+
+  /// Returns a file that transitively `#use`s itself, if there is any such file.
+  pub fn use_cycle(&self) -> Option<Uri> {
+    self.ordering().cycle.map(|id| self.uris[id].clone())
+  }
+
   /// Returns the location of the definition of the thing being pointed at.
   pub fn go_to_def(&self, uri: &Uri, pos: Position) -> Option<Location> {
     go_to_def::get(self, uri, pos)
@@ -133,161 +396,549 @@ impl Db {
   pub fn hover(&self, uri: &Uri, pos: Position) -> Option<Hover> {
     hover::get(self, uri, pos)
   }
+
+  /// Returns the completions available at the position.
+  pub fn completions(
+    &self,
+    uri: &Uri,
+    pos: Position,
+  ) -> Option<Vec<Completion>> {
+    completions::get(self, uri, pos)
+  }
+
+  /// Returns the semantic tokens of the file at the given URI, optionally only
+  /// those in the given range.
+  pub fn semantic_tokens(
+    &self,
+    uri: &Uri,
+    range: Option<Range>,
+  ) -> Option<Vec<SemanticToken>> {
+    semantic_tokens::get(self, uri, range)
+  }
+
+  /// Returns the ranges that can be folded in the file at the given URI.
+  pub fn folding_ranges(&self, uri: &Uri) -> Option<Vec<FoldingRange>> {
+    folding_ranges::get(self, uri)
+  }
+
+  /// Returns, for each position, the ranges of successively larger syntax
+  /// nodes containing it, starting with the innermost.
+  pub fn selection_ranges(
+    &self,
+    uri: &Uri,
+    positions: &[Position],
+  ) -> Option<Vec<Vec<Range>>> {
+    selection_ranges::get(self, uri, positions)
+  }
+
+  /// Returns every call from one function to another in every file.
+  pub fn calls(&self) -> Vec<Call> {
+    call_hierarchy::calls(self)
+  }
+
+  /// Returns the function being pointed at, either by its definition or by a
+  /// call to it.
+  pub fn fn_item(&self, uri: &Uri, pos: Position) -> Option<FnItem> {
+    call_hierarchy::prepare(self, uri, pos)
+  }
+
+  /// Returns the functions that call the given function, along with the
+  /// ranges of those calls.
+  pub fn incoming_calls(&self, item: &FnItem) -> Vec<(FnItem, Vec<Range>)> {
+    call_hierarchy::incoming(self, item)
+  }
+
+  /// Returns the functions that the given function calls, along with the
+  /// ranges of those calls.
+  pub fn outgoing_calls(&self, item: &FnItem) -> Vec<(FnItem, Vec<Range>)> {
+    call_hierarchy::outgoing(self, item)
+  }
+
+  /// Returns an estimate of how much memory is used by the files and what we
+  /// have computed about them so far.
+  pub fn memory_usage(&self) -> MemoryUsage {
+    memory_usage::get(self)
+  }
+
+  /// Returns the syntax data of the file, lexing, parsing, and lowering it if
+  /// needed.
+  pub(crate) fn syntax_data(&self, id: UriId) -> Rc<SyntaxData> {
+    let file = &self.files[&id];
+    file
+      .syntax
+      .borrow_mut()
+      .get_or_insert_with(|| Rc::new(get_syntax_data(&file.contents)))
+      .clone()
+  }
+
+  /// Returns the resolved `#use`s of the file.
+  pub(crate) fn uses(&self, id: UriId) -> Rc<uses::Uses> {
+    let file = &self.files[&id];
+    if let Some(ret) = file.uses.borrow().as_ref() {
+      return ret.clone();
+    }
+    let ret = Rc::new(uses::get(
+      &self.uris,
+      id,
+      self.syntax_data(id).uses.clone(),
+      &self.config.include_dirs,
+    ));
+    *file.uses.borrow_mut() = Some(ret.clone());
+    ret
+  }
+
+  /// Returns the scopes the file is checked in: each target it is in, or just
+  /// on its own if it is in none.
+  pub(crate) fn scopes(&self, id: UriId) -> Vec<Scope> {
+    let ret: Vec<_> = self
+      .targets
+      .iter()
+      .enumerate()
+      .filter(|(_, t)| t.memos.contains_key(&id))
+      .map(|(idx, _)| Some(idx))
+      .collect();
+    if ret.is_empty() {
+      vec![None]
+    } else {
+      ret
+    }
+  }
+
+  /// Returns the scope to use for queries about a single file, like hover: the
+  /// first target it is in, if any.
+  fn scope(&self, id: UriId) -> Scope {
+    self.targets.iter().position(|t| t.memos.contains_key(&id))
+  }
+
+  fn memos(&self, id: UriId, scope: Scope) -> &Memos {
+    match scope {
+      None => &self.files[&id].memos,
+      Some(idx) => &self.targets[idx].memos[&id],
+    }
+  }
+
+  /// Returns the semantic data of the file, or `None` if the file
+  /// transitively `#use`s itself.
+  pub(crate) fn semantic_data(&self, id: UriId) -> Option<Rc<SemanticData>> {
+    self.semantic_memo(id, self.scope(id)).map(|(sd, _)| sd)
+  }
+
+  /// Returns the semantic data of the file in the scope, and the revision at
+  /// which its exports last changed.
+  fn semantic_memo(
+    &self,
+    id: UriId,
+    scope: Scope,
+  ) -> Option<(Rc<SemanticData>, Revision)> {
+    if let Some(memo) = self.memos(id, scope).semantic.borrow().as_ref() {
+      if memo.verified_at == self.revision {
+        return Some((memo.value.clone(), memo.changed_at));
+      }
+    }
+    if self.active.borrow().contains(&(id, scope)) {
+      return None;
+    }
+    self.active.borrow_mut().push((id, scope));
+    let _active = Active(&self.active);
+    self.semantic_memo_verify(id, scope)
+  }
+
+  fn semantic_memo_verify(
+    &self,
+    id: UriId,
+    scope: Scope,
+  ) -> Option<(Rc<SemanticData>, Revision)> {
+    // in a target, the file sees the file before it, which in turn sees the
+    // ones before that. like `cc0`, we don't load a file twice, so we skip the
+    // `#use`s of files the files before it already loaded.
+    let mut prev = None::<(Rc<SemanticData>, Revision)>;
+    let mut loaded = FxHashSet::default();
+    if let Some(idx) = scope {
+      let files = &self.targets[idx].files;
+      let pos = files.iter().position(|&x| x == id).expect("not in target");
+      if let Some(&prev_id) = pos.checked_sub(1).and_then(|p| files.get(p)) {
+        prev = Some(self.semantic_memo(prev_id, scope)?);
+        loaded = self.loaded(&files[..pos]);
+      }
+    }
+    let uses = self.uses(id);
+    let mut deps = map_with_capacity(uses.uses.len());
+    for u in uses.uses.iter() {
+      if let UseKind::File(dep) = u.kind {
+        if !loaded.contains(&dep) {
+          deps.insert(dep, self.semantic_memo(dep, None)?);
+        }
+      }
+    }
+    let file = &self.files[&id];
+    let memos = self.memos(id, scope);
+    let old = memos.semantic.borrow_mut().take();
+    let memo = match old {
+      Some(mut old)
+        if file.changed_at <= old.verified_at
+          && self.uris_changed_at <= old.verified_at
+          && prev
+            .iter()
+            .chain(deps.values())
+            .all(|&(_, changed_at)| changed_at <= old.verified_at) =>
+      {
+        old.verified_at = self.revision;
+        old
+      }
+      _ => {
+        let value = get_semantic_data(
+          &mut self.cx.borrow_mut(),
+          &mut memos.bodies.borrow_mut(),
+          &self.std_lib,
+          get_file_id(&self.uris, id),
+          prev.as_ref().map(|(sd, _)| &sd.env),
+          &uses.uses,
+          &deps,
+          &self.syntax_data(id).hir_root,
+        );
+        let changed_at = match old {
+          Some(old) if old.value.env.same_exports(&value.env) => old.changed_at,
+          _ => self.revision,
+        };
+        Memo {
+          value: Rc::new(value),
+          verified_at: self.revision,
+          changed_at,
+        }
+      }
+    };
+    let ret = (memo.value.clone(), memo.changed_at);
+    *memos.semantic.borrow_mut() = Some(memo);
+    Some(ret)
+  }
+
+  /// Returns the files, and all the files they transitively `#use`.
+  fn loaded(&self, files: &[UriId]) -> FxHashSet<UriId> {
+    let mut ret = FxHashSet::default();
+    let mut stack = files.to_vec();
+    while let Some(id) = stack.pop() {
+      if ret.insert(id) {
+        stack.extend(file_deps(&self.uses(id).uses));
+      }
+    }
+    ret
+  }
+
+  /// Returns the diagnostics of the file in the scope and their hash, assuming
+  /// it does not transitively `#use` itself.
+  pub(crate) fn diagnostics(
+    &self,
+    id: UriId,
+    scope: Scope,
+  ) -> (Rc<Vec<Diagnostic>>, u64) {
+    let (semantic_data, _) = self
+      .semantic_memo(id, scope)
+      .expect("diagnostics for file in cycle");
+    let memos = self.memos(id, scope);
+    if let Some(memo) = memos.diagnostics.borrow().as_ref() {
+      if Rc::ptr_eq(&memo.semantic_data, &semantic_data) {
+        return (memo.value.clone(), memo.hash);
+      }
+    }
+    let value = Rc::new(all_diagnostics::get_diagnostics(
+      &self.syntax_data(id),
+      &self.uses(id),
+      &semantic_data,
+      &self.tys(),
+      &self.config,
+    ));
+    let hash = all_diagnostics::hash(&value);
+    *memos.diagnostics.borrow_mut() = Some(DiagnosticsMemo {
+      semantic_data,
+      value: value.clone(),
+      hash,
+    });
+    (value, hash)
+  }
+
+  /// Returns an ordering of all the files such that every file comes after the
+  /// files it `#use`s, or an arbitrary ordering and a file in a cycle if there
+  /// is no such ordering.
+  pub(crate) fn ordering(&self) -> Rc<Ordering> {
+    if let Some(ret) = self.ordering.borrow().as_ref() {
+      return ret.clone();
+    }
+    let graph: Graph<_> = self
+      .files
+      .keys()
+      .map(|&id| (id, file_deps(&self.uses(id).uses)))
+      .collect();
+    let ret = match topo_sort::get(&graph) {
+      Ok(ids) => Ordering { ids, cycle: None },
+      Err(e) => {
+        // conjure up a stable but arbitrary ordering.
+        let mut ids: Vec<_> = self.uris.iter().collect();
+        ids.sort_unstable();
+        Ordering {
+          ids,
+          cycle: Some(e.witness()),
+        }
+      }
+    };
+    let ret = Rc::new(ret);
+    *self.ordering.borrow_mut() = Some(ret.clone());
+    ret
+  }
+
+  /// Returns the types. Don't hold on to this while computing semantic data.
+  pub(crate) fn tys(&self) -> Ref<'_, TyDb> {
+    Ref::map(self.cx.borrow(), |cx| &cx.tys)
+  }
+
+  /// Updates which files of the targets are in the `Db`.
+  fn resolve_targets(&mut self) {
+    for target in self.targets.iter_mut() {
+      let mut seen = FxHashSet::default();
+      target.files = target
+        .target
+        .files
+        .iter()
+        .filter_map(|uri| self.uris.get_id(uri))
+        .filter(|id| self.files.contains_key(id) && seen.insert(*id))
+        .collect();
+      target.memos.retain(|id, _| seen.contains(id));
+      for &id in target.files.iter() {
+        target.memos.entry(id).or_default();
+      }
+    }
+  }
+}
+
+type Revision = u64;
+
+/// Pops the top of the stack of files whose semantic data is being computed
+/// when dropped, even if the computing panicked, so a later query doesn't
+/// think the file is in a cycle.
+#[derive(Debug)]
+struct Active<'a>(&'a RefCell<Vec<(UriId, Scope)>>);
+
+impl Drop for Active<'_> {
+  fn drop(&mut self) {
+    self.0.borrow_mut().pop();
+  }
+}
+
+/// Where a file is checked: on its own, or as part of the target with this
+/// index.
+pub(crate) type Scope = Option<usize>;
+
+/// A file, and the memoized queries on it.
+#[derive(Debug)]
+pub(crate) struct File {
+  /// if the file is open, the contents of the editor's buffer. else, the
+  /// contents on disk.
+  pub(crate) contents: String,
+  /// whether the file is open in an editor.
+  open: bool,
+  /// if the file is open, the contents on disk, or `None` if it's not on disk.
+  pub(crate) disk: Option<String>,
+  /// the last revision in which `contents` changed.
+  changed_at: Revision,
+  pub(crate) syntax: RefCell<Option<Rc<SyntaxData>>>,
+  uses: RefCell<Option<Rc<uses::Uses>>>,
+  /// the memoized queries when checked on its own.
+  pub(crate) memos: Memos,
+}
+
+impl File {
+  fn new(contents: String, revision: Revision) -> Self {
+    Self {
+      contents,
+      open: false,
+      disk: None,
+      changed_at: revision,
+      syntax: RefCell::default(),
+      uses: RefCell::default(),
+      memos: Memos::default(),
+    }
+  }
+}
+
+/// The memoized queries on a file that depend on the scope it is checked in.
+#[derive(Debug, Default)]
+pub(crate) struct Memos {
+  pub(crate) semantic: RefCell<Option<Memo<SemanticData>>>,
+  /// the checked fn bodies, reused when the env is re-computed.
+  bodies: RefCell<Bodies>,
+  pub(crate) diagnostics: RefCell<Option<DiagnosticsMemo>>,
+}
+
+/// A target, and the memoized queries on its files.
+#[derive(Debug)]
+pub(crate) struct TargetData {
+  pub(crate) target: Target,
+  /// the files of the target that are in the `Db`, in order, without
+  /// duplicates.
+  files: Vec<UriId>,
+  pub(crate) memos: FxHashMap<UriId, Memos>,
+}
+
+/// A memoized value.
+#[derive(Debug)]
+pub(crate) struct Memo<T> {
+  pub(crate) value: Rc<T>,
+  /// the last revision in which we checked this was up to date.
+  verified_at: Revision,
+  /// the last revision in which this changed, as far as dependents care.
+  changed_at: Revision,
+}
+
+/// Memoized diagnostics, and the semantic data they were computed from.
+#[derive(Debug)]
+pub(crate) struct DiagnosticsMemo {
+  semantic_data: Rc<SemanticData>,
+  pub(crate) value: Rc<Vec<Diagnostic>>,
+  hash: u64,
+}
+
+/// An ordering of the files.
+#[derive(Debug)]
+pub(crate) struct Ordering {
+  pub(crate) ids: Vec<UriId>,
+  /// a file in a use cycle, if there was one.
+  pub(crate) cycle: Option<UriId>,
 }
 
 fn map_with_capacity<K, V>(cap: usize) -> FxHashMap<K, V> {
   FxHashMap::with_capacity_and_hasher(cap, BuildHasherDefault::default())
 }
 
-fn get_syntax_data(contents: String) -> SyntaxData {
-  let lexed = lex::get(&contents);
+fn get_syntax_data(contents: &str) -> SyntaxData {
+  let lexed = lex::get(contents);
   let parsed = parse::get(&lexed.tokens);
-  let lowered = lower::get(parsed.root.clone());
-  let positions = PositionDb::new(&contents);
   // satisfy borrowck
   let lexed_uses = lexed.uses;
   let lexed_errors = lexed.errors;
-  SyntaxData {
-    contents,
-    positions,
-    ast_root: parsed.root,
-    hir_root: lowered.root,
-    uses: lexed_uses,
-    ptrs: lowered.ptrs,
-    errors: SyntaxErrors {
-      lex: lexed_errors,
-      parse: parsed.errors,
-      lower: lowered.errors,
-    },
-  }
+  SyntaxData::new(
+    PositionDb::new(contents),
+    parsed.root,
+    lexed_uses,
+    lexed_errors,
+    parsed.errors,
+  )
 }
 
-fn get_all_semantic_data(
-  uris: UriDb,
-  syntax_data: FxHashMap<UriId, SyntaxData>,
-) -> Db {
-  let mut uses = map_with_capacity(syntax_data.len());
-  let mut uses_errors = map_with_capacity(syntax_data.len());
-  for (&id, sd) in syntax_data.iter() {
-    let us = uses::get(&uris, id, sd.uses.clone());
-    assert!(uses.insert(id, us.uses).is_none());
-    assert!(uses_errors.insert(id, us.errors).is_none());
-  }
-  // determine a topo ordering of the file dependencies.
-  let graph: Graph<_> = syntax_data
-    .keys()
-    .map(|&id| {
-      let neighbors = uses[&id]
-        .iter()
-        .filter_map(|u| match u.kind {
-          UseKind::File(id) => Some(id),
-          UseKind::Lib(_) => None,
-        })
-        .collect();
-      (id, neighbors)
-    })
-    .collect();
-  let ordering = match topo_sort::get(&graph) {
-    Ok(x) => x,
-    Err(e) => {
-      // give up on further processing. conjure up a stable but arbitrary
-      // ordering.
-      let mut ordering: Vec<_> = uris.iter().collect();
-      ordering.sort_unstable();
-      return Db {
-        uris,
-        syntax_data,
-        ordering,
-        kind: DbKind::CycleError(e.witness()),
-      };
-    }
-  };
-  drop(graph);
-  // run statics in the order of the topo order, update errors.
-  let (mut cx, std_lib) = std_lib::get();
-  let mut semantic_data =
-    map_with_capacity::<UriId, SemanticData>(syntax_data.len());
-  for &id in ordering.iter() {
-    let mut import = Env::with_main();
-    let mut import_errors = Vec::new();
-    for u in uses[&id].iter() {
-      let env = match u.kind {
-        UseKind::File(id) => &semantic_data[&id].env,
-        UseKind::Lib(lib) => std_lib.get(lib),
-      };
-      let mut errors = Vec::new();
-      statics::add_env(&mut cx, &mut errors, &mut import, env);
-      import_errors.extend(errors.into_iter().map(|kind| ImportError {
-        range: u.range,
-        kind,
-      }));
-    }
-    // we used to store this directly in the id itself, but that's a bit of a
-    // pain. could go back to doing that as a micro-optimization.
-    let is_header = std::path::Path::new(uris[id].path())
-      .extension()
-      .map_or(true, |x| x == "h0");
-    let file = if is_header {
-      FileId::Header(id)
-    } else {
-      FileId::Source(id)
-    };
-    let env = statics::get(&mut cx, import, file, &syntax_data[&id].hir_root);
-    semantic_data.insert(
-      id,
-      SemanticData {
-        env,
-        uses_errors: uses_errors.remove(&id).expect("missing uses errors"),
-        import_errors,
-        statics_errors: std::mem::take(&mut cx.errors),
+/// Runs the statics on the file with the given id, after the items of `prev`,
+/// if any. `deps` must have the semantic data of all the files it uses, except
+/// those already in `prev`.
+#[allow(clippy::too_many_arguments)]
+fn get_semantic_data(
+  cx: &mut Cx,
+  bodies: &mut Bodies,
+  std_lib: &StdLib,
+  file: FileId,
+  prev: Option<&Env>,
+  uses: &[uses::Use],
+  deps: &FxHashMap<UriId, (Rc<SemanticData>, Revision)>,
+  hir_root: &hir::Root,
+) -> SemanticData {
+  // a check that panicked partway through may have left its errors here, and
+  // their ids are for some other file.
+  cx.errors.clear();
+  let mut import = Env::with_main();
+  if let Some(prev) = prev {
+    // any errors here were already reported for the files before this one.
+    statics::add_env(cx, &mut Vec::new(), &mut import, prev);
+  }
+  let mut import_errors = Vec::new();
+  for u in uses.iter() {
+    let lib_env;
+    let env = match u.kind {
+      UseKind::File(id) => match deps.get(&id) {
+        Some((sd, _)) => &sd.env,
+        None => continue,
       },
-    );
+      UseKind::Lib(lib) => {
+        lib_env = std_lib.get(cx, lib);
+        lib_env.as_ref()
+      }
+    };
+    let mut errors = Vec::new();
+    statics::add_env(cx, &mut errors, &mut import, env);
+    import_errors.extend(errors.into_iter().map(|kind| ImportError {
+      range: u.range,
+      kind,
+    }));
   }
-  // return.
-  Db {
-    uris,
-    syntax_data,
-    ordering,
-    kind: DbKind::Done(Box::new(Done { cx, semantic_data })),
+  let env = statics::get(cx, import, bodies, file, hir_root);
+  SemanticData {
+    env,
+    import_errors,
+    statics_errors: std::mem::take(&mut cx.errors),
   }
 }
 
-#[derive(Debug)]
-pub(crate) enum DbKind {
-  CycleError(UriId),
-  Done(Box<Done>),
-}
-
-impl DbKind {
-  pub(crate) fn done(&self) -> Option<&Done> {
-    match *self {
-      DbKind::CycleError(_) => None,
-      DbKind::Done(ref done) => Some(done.as_ref()),
-    }
+fn get_file_id(uris: &UriDb, id: UriId) -> FileId {
+  // we used to store this directly in the id itself, but that's a bit of a
+  // pain. could go back to doing that as a micro-optimization.
+  //
+  // files with no extension are usually new, unsaved buffers, which are more
+  // likely to be sources.
+  let is_header = paths::extension(&uris[id]) == Some("h0");
+  if is_header {
+    FileId::Header(id)
+  } else {
+    FileId::Source(id)
   }
 }
 
-/// Information contained in a 'done' database that had no cycle errors.
-#[derive(Debug)]
-pub(crate) struct Done {
-  pub(crate) cx: Cx,
-  pub(crate) semantic_data: FxHashMap<UriId, SemanticData>,
+/// Returns the files that the uses use.
+fn file_deps(uses: &[uses::Use]) -> BTreeSet<UriId> {
+  uses
+    .iter()
+    .filter_map(|u| match u.kind {
+      UseKind::File(id) => Some(id),
+      UseKind::Lib(_) => None,
+    })
+    .collect()
 }
 
 /// Syntax data for a file.
 ///
-/// Everything in this struct is derived from `contents`.
+/// Everything in this struct is derived from the contents of the file.
 #[derive(Debug)]
 pub(crate) struct SyntaxData {
-  pub(crate) contents: String,
   pub(crate) positions: PositionDb,
   pub(crate) ast_root: AstRoot,
   pub(crate) hir_root: hir::Root,
   pub(crate) ptrs: Ptrs,
   pub(crate) uses: Vec<syntax::Use>,
+  pub(crate) calls: Vec<CallSite>,
   pub(crate) errors: SyntaxErrors,
 }
 
+impl SyntaxData {
+  /// Returns the syntax data for the lexed and parsed file, lowering it.
+  pub(crate) fn new(
+    positions: PositionDb,
+    ast_root: AstRoot,
+    uses: Vec<syntax::Use>,
+    lex_errors: Vec<lex::Error>,
+    parse_errors: Vec<parse::Error>,
+  ) -> Self {
+    let lowered = lower::get(ast_root.clone());
+    let calls = call_hierarchy::index(&ast_root, &lowered.root, &lowered.ptrs);
+    SyntaxData {
+      positions,
+      ast_root,
+      hir_root: lowered.root,
+      uses,
+      ptrs: lowered.ptrs,
+      calls,
+      errors: SyntaxErrors {
+        lex: lex_errors,
+        parse: parse_errors,
+        lower: lowered.errors,
+      },
+    }
+  }
+}
+
 /// Syntax errors from a file.
 #[derive(Debug)]
 pub(crate) struct SyntaxErrors {
@@ -300,7 +951,6 @@ pub(crate) struct SyntaxErrors {
 #[derive(Debug)]
 pub(crate) struct SemanticData {
   pub(crate) env: Env,
-  pub(crate) uses_errors: Vec<uses::Error>,
   pub(crate) import_errors: Vec<ImportError>,
   pub(crate) statics_errors: Vec<statics::Error>,
 }
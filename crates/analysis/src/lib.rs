@@ -10,10 +10,19 @@
 mod tests;
 
 mod db;
+mod lints;
+mod position_db;
 mod queries;
+mod reparse;
 mod types;
 mod util;
 
 pub use db::Db;
+pub use fmt::Style;
 pub use text_pos::{Position, Range};
-pub use types::{CodeBlock, Diagnostic, Edit, Hover, Location, Update};
+pub use types::{
+  Call, CodeBlock, Completion, CompletionKind, Config, Diagnostic, Edit,
+  FnItem, FoldingRange, FoldingRangeKind, Hover, Level, Lints, Location,
+  MemoryUsage, SemanticToken, SemanticTokenKind, Severity, Target, Update,
+  Verbosity,
+};
@@ -0,0 +1,74 @@
+//! Checks that depend on the config: the constructs not allowed at the
+//! language level, and the lints.
+//!
+//! These only look at the HIR of a single file, so unlike the statics they
+//! don't depend on the other files.
+
+use crate::types::{Config, Level, Severity};
+use hir::{AssignOp, BinOp, Expr, Root, Simp, Stmt};
+use statics::Id;
+
+/// A problem found by a check.
+#[derive(Debug)]
+pub(crate) struct Lint {
+  pub(crate) id: Id,
+  pub(crate) severity: Severity,
+  pub(crate) message: &'static str,
+}
+
+/// Returns the problems in the file, as configured.
+pub(crate) fn get(root: &Root, config: &Config) -> Vec<Lint> {
+  let mut ret = Vec::new();
+  if config.level == Level::C0 {
+    for (id, stmt) in root.arenas.stmt.iter() {
+      let message = match stmt {
+        Stmt::Break => "cannot use `break` in C0",
+        Stmt::Continue => "cannot use `continue` in C0",
+        _ => continue,
+      };
+      ret.push(Lint {
+        id: Id::Stmt(id),
+        severity: Severity::Error,
+        message,
+      });
+    }
+  }
+  if config.lints.suspicious {
+    for (id, simp) in root.arenas.simp.iter() {
+      if let Simp::Assign(lhs, AssignOp::Eq, rhs) = *simp {
+        if same_name(root, lhs, rhs) {
+          ret.push(Lint {
+            id: Id::Simp(id),
+            severity: Severity::Warning,
+            message: "assigning a variable to itself",
+          });
+        }
+      }
+    }
+  }
+  if config.lints.style {
+    for (id, expr) in root.arenas.expr.iter() {
+      if let Expr::BinOp(lhs, BinOp::Eq | BinOp::Neq, rhs) = *expr {
+        let arenas = &root.arenas;
+        if matches!(arenas.expr[lhs], Expr::Bool)
+          || matches!(arenas.expr[rhs], Expr::Bool)
+        {
+          ret.push(Lint {
+            id: Id::Expr(id),
+            severity: Severity::Warning,
+            message: "comparison with a `bool` literal",
+          });
+        }
+      }
+    }
+  }
+  ret
+}
+
+/// Returns whether both expressions are the same variable.
+fn same_name(root: &Root, lhs: hir::ExprId, rhs: hir::ExprId) -> bool {
+  match (&root.arenas.expr[lhs], &root.arenas.expr[rhs]) {
+    (Expr::Name(lhs), Expr::Name(rhs)) => lhs == rhs,
+    _ => false,
+  }
+}
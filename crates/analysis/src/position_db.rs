@@ -0,0 +1,184 @@
+//! Conversion between byte offsets and the line/character positions used by
+//! the language server protocol.
+//!
+//! Characters are counted in UTF-16 code units, as the protocol requires. We
+//! don't keep a copy of the text; we only need to know where the lines start
+//! and where the non-ASCII characters are, since those are the only ones that
+//! are more than one byte long.
+
+use syntax::rowan::{TextRange, TextSize};
+use text_pos::{Position, Range};
+
+/// A database of the lines in some text.
+#[derive(Debug)]
+pub(crate) struct PositionDb {
+  /// the length of the text.
+  len: u32,
+  /// the offset of the start of each line. the first is always 0.
+  line_starts: Vec<u32>,
+  /// the non-ASCII characters, sorted by offset.
+  wide_chars: Vec<WideChar>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct WideChar {
+  offset: u32,
+  len_utf8: u8,
+  len_utf16: u8,
+}
+
+impl WideChar {
+  fn end(&self) -> u32 {
+    self.offset + u32::from(self.len_utf8)
+  }
+}
+
+impl PositionDb {
+  pub(crate) fn new(text: &str) -> Self {
+    let mut line_starts = vec![0];
+    line_starts.extend(newline_ends(text, 0));
+    let mut wide_chars: Vec<_> = wide_chars(text, 0).collect();
+    line_starts.shrink_to_fit();
+    wide_chars.shrink_to_fit();
+    Self {
+      len: text.len() as u32,
+      line_starts,
+      wide_chars,
+    }
+  }
+
+  /// Replaces the text in the range with the new text. Only the starts of the
+  /// lines in the range are re-computed; the ones after are just shifted.
+  pub(crate) fn edit(&mut self, range: TextRange, text: &str) {
+    let start = u32::from(range.start());
+    let end = u32::from(range.end());
+    let new_len = text.len() as u32;
+    self.len = self.len - (end - start) + new_len;
+    // the lines that started inside the old text.
+    let lo = self.line_starts.partition_point(|&x| x <= start);
+    let hi = self.line_starts.partition_point(|&x| x <= end);
+    let new = newline_ends(text, start);
+    let new_hi = lo + new.clone().count();
+    self.line_starts.splice(lo..hi, new);
+    for x in self.line_starts[new_hi..].iter_mut() {
+      *x = *x - (end - start) + new_len;
+    }
+    // likewise for the wide chars.
+    let lo = self.wide_chars.partition_point(|w| w.offset < start);
+    let hi = self.wide_chars.partition_point(|w| w.offset < end);
+    let new = wide_chars(text, start);
+    let new_hi = lo + new.clone().count();
+    self.wide_chars.splice(lo..hi, new);
+    for w in self.wide_chars[new_hi..].iter_mut() {
+      w.offset = w.offset - (end - start) + new_len;
+    }
+  }
+
+  /// Returns an estimate of the number of bytes this uses on the heap.
+  pub(crate) fn heap_size(&self) -> usize {
+    self.line_starts.capacity() * std::mem::size_of::<u32>()
+      + self.wide_chars.capacity() * std::mem::size_of::<WideChar>()
+  }
+
+  fn position(&self, text_size: TextSize) -> Option<Position> {
+    let offset = u32::from(text_size);
+    if offset > self.len {
+      return None;
+    }
+    let line = match self.line_starts.binary_search(&offset) {
+      Ok(x) => x,
+      Err(x) => x - 1,
+    };
+    let start = self.line_starts[line];
+    let mut character = offset - start;
+    for w in self.wide_chars_in(start, offset) {
+      // not on a char boundary.
+      if offset < w.end() {
+        return None;
+      }
+      character -= u32::from(w.len_utf8 - w.len_utf16);
+    }
+    Some(Position {
+      line: line as u32,
+      character,
+    })
+  }
+
+  pub(crate) fn text_size(&self, pos: Position) -> Option<TextSize> {
+    let start = *self.line_starts.get(pos.line as usize)?;
+    let end = self
+      .line_starts
+      .get(pos.line as usize + 1)
+      .copied()
+      .unwrap_or(self.len);
+    // walk the line one run of ASCII and one wide char at a time. `utf16` is
+    // always at most `pos.character` at the start of each iteration.
+    let mut byte = start;
+    let mut utf16 = 0u32;
+    for w in self.wide_chars_in(start, end) {
+      let ascii = w.offset - byte;
+      if utf16 + ascii >= pos.character {
+        return Some(TextSize::from(byte + (pos.character - utf16)));
+      }
+      utf16 += ascii + u32::from(w.len_utf16);
+      byte = w.end();
+      if utf16 >= pos.character {
+        return Some(TextSize::from(byte));
+      }
+    }
+    let ascii = end - byte;
+    if utf16 + ascii >= pos.character {
+      Some(TextSize::from(byte + (pos.character - utf16)))
+    } else {
+      None
+    }
+  }
+
+  pub(crate) fn range(&self, text_range: TextRange) -> Option<Range> {
+    Some(Range {
+      start: self.position(text_range.start())?,
+      end: self.position(text_range.end())?,
+    })
+  }
+
+  /// Returns `None` if either end is not in the text, or if the range is
+  /// backwards.
+  pub(crate) fn text_range(&self, range: Range) -> Option<TextRange> {
+    let start = self.text_size(range.start)?;
+    let end = self.text_size(range.end)?;
+    (start <= end).then(|| TextRange::new(start, end))
+  }
+
+  /// Returns the wide chars that start in `start..end`.
+  fn wide_chars_in(&self, start: u32, end: u32) -> &[WideChar] {
+    let lo = self.wide_chars.partition_point(|w| w.offset < start);
+    let hi = self.wide_chars.partition_point(|w| w.offset < end);
+    &self.wide_chars[lo..hi]
+  }
+}
+
+/// Returns the offsets just after each newline in the text, which starts at
+/// `base`.
+fn newline_ends(
+  text: &str,
+  base: u32,
+) -> impl Iterator<Item = u32> + Clone + '_ {
+  text
+    .match_indices('\n')
+    .map(move |(idx, _)| base + idx as u32 + 1)
+}
+
+/// Returns the wide chars in the text, which starts at `base`.
+fn wide_chars(
+  text: &str,
+  base: u32,
+) -> impl Iterator<Item = WideChar> + Clone + '_ {
+  text
+    .char_indices()
+    .filter(|(_, c)| !c.is_ascii())
+    .map(move |(idx, c)| WideChar {
+      offset: base + idx as u32,
+      len_utf8: c.len_utf8() as u8,
+      len_utf16: c.len_utf16() as u8,
+    })
+}
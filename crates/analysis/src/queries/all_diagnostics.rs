@@ -1,49 +1,100 @@
-use crate::db::{Db, DbKind, SemanticData, SyntaxData};
-use crate::types::Diagnostic;
+use crate::db::{Db, Ordering, Scope, SemanticData, SyntaxData};
+use crate::lints;
+use crate::types::{Config, Diagnostic, Severity, Verbosity};
 use lower::Ptrs;
+use rustc_hash::FxHasher;
 use statics::{Id, TyDb};
+use std::collections::BTreeMap;
+use std::hash::{Hash as _, Hasher as _};
+use std::rc::Rc;
 use syntax::ast::{AstNode, Root};
 use syntax::rowan::TextRange;
 use text_pos::{Position, Range};
 use uri_db::{Uri, UriId};
 
-pub(crate) fn get(db: &Db) -> Vec<(Uri, Vec<Diagnostic>)> {
-  match db.kind {
-    DbKind::Done(ref done) => db
-      .ordering
-      .iter()
-      .map(|&id| {
-        let ds = get_diagnostics(
-          &db.syntax_data[&id],
-          &done.semantic_data[&id],
-          &done.cx.tys,
-        );
-        (db.uris[id].clone(), ds)
-      })
-      .collect(),
-    DbKind::CycleError(witness) => db
-      .ordering
-      .iter()
-      .map(|&id| {
-        let ds = get_diagnostics_cycle_error(&db.syntax_data[&id], id, witness);
-        (db.uris[id].clone(), ds)
-      })
-      .collect(),
+pub(crate) fn get(
+  db: &Db,
+) -> impl ExactSizeIterator<Item = (Uri, Vec<Diagnostic>, u64)> + '_ {
+  let ordering = db.ordering();
+  ordering.ids.clone().into_iter().map(move |id| {
+    let (ds, hash) = get_one(db, &ordering, id);
+    (db.uris[id].clone(), ds.as_ref().clone(), hash)
+  })
+}
+
+/// Returns the diagnostics of the file, and their hash.
+pub(crate) fn get_one(
+  db: &Db,
+  ordering: &Ordering,
+  id: UriId,
+) -> (Rc<Vec<Diagnostic>>, u64) {
+  match ordering.cycle {
+    None => {
+      let scopes = db.scopes(id);
+      match scopes.as_slice() {
+        &[scope] => db.diagnostics(id, scope),
+        _ => {
+          let ds = get_diagnostics_targets(db, id, &scopes);
+          let hash = hash(&ds);
+          (Rc::new(ds), hash)
+        }
+      }
+    }
+    Some(witness) => {
+      let ds = get_diagnostics_cycle_error(&db.syntax_data(id), id, witness);
+      let hash = hash(&ds);
+      (Rc::new(ds), hash)
+    }
+  }
+}
+
+/// Returns the diagnostics of a file in many targets. Those in only some of the
+/// targets say which targets those are.
+fn get_diagnostics_targets(
+  db: &Db,
+  id: UriId,
+  scopes: &[Scope],
+) -> Vec<Diagnostic> {
+  let mut in_targets = BTreeMap::<Diagnostic, Vec<&str>>::new();
+  for &scope in scopes {
+    let name = scope.map_or("", |idx| db.targets[idx].target.name.as_str());
+    for d in db.diagnostics(id, scope).0.iter() {
+      in_targets.entry(d.clone()).or_default().push(name);
+    }
   }
+  in_targets
+    .into_iter()
+    .map(|(mut d, names)| {
+      if names.len() != scopes.len() {
+        let names: Vec<_> = names.iter().map(|x| format!("`{x}`")).collect();
+        d.message = format!("{} (in {})", d.message, names.join(", "));
+      }
+      d
+    })
+    .collect()
 }
 
-fn get_diagnostics(
+/// Returns a hash of the diagnostics.
+pub(crate) fn hash(diagnostics: &[Diagnostic]) -> u64 {
+  let mut h = FxHasher::default();
+  for d in diagnostics {
+    let (start, end) = (d.range.start, d.range.end);
+    (start.line, start.character, end.line, end.character).hash(&mut h);
+    d.message.hash(&mut h);
+    d.severity.hash(&mut h);
+  }
+  h.finish()
+}
+
+pub(crate) fn get_diagnostics(
   syntax_data: &SyntaxData,
+  uses: &uses::Uses,
   semantic_data: &SemanticData,
   tys: &TyDb,
+  config: &Config,
 ) -> Vec<Diagnostic> {
-  get_syntax_diagnostics(syntax_data)
-    .chain(
-      semantic_data
-        .uses_errors
-        .iter()
-        .map(|x| (x.range, x.kind.to_string())),
-    )
+  let errors = get_syntax_diagnostics(syntax_data)
+    .chain(uses.errors.iter().map(|x| (x.range, x.kind.to_string())))
     .chain(
       semantic_data
         .import_errors
@@ -55,10 +106,24 @@ fn get_diagnostics(
         get_text_range(&syntax_data.ptrs, &syntax_data.ast_root, x.id);
       (range, x.kind.display(tys).to_string())
     }))
-    .filter_map(|(rng, message)| {
+    .map(|(rng, message)| (rng, message, Severity::Error));
+  let lints = lints::get(&syntax_data.hir_root, config)
+    .into_iter()
+    .map(|x| {
+      let range =
+        get_text_range(&syntax_data.ptrs, &syntax_data.ast_root, x.id);
+      (range, x.message.to_owned(), x.severity)
+    });
+  errors
+    .chain(lints)
+    .filter(|&(_, _, severity)| {
+      config.verbosity == Verbosity::Normal || severity == Severity::Error
+    })
+    .filter_map(|(rng, message, severity)| {
       Some(Diagnostic {
         range: syntax_data.positions.range(rng)?,
         message,
+        severity,
       })
     })
     .collect()
@@ -74,6 +139,7 @@ fn get_diagnostics_cycle_error(
       Some(Diagnostic {
         range: syntax_data.positions.range(rng)?,
         message,
+        severity: Severity::Error,
       })
     })
     .collect();
@@ -85,6 +151,7 @@ fn get_diagnostics_cycle_error(
     ret.push(Diagnostic {
       range: Range { start: z, end: z },
       message: "cannot have a use cycle involving this file".to_owned(),
+      severity: Severity::Error,
     })
   }
   ret
@@ -106,10 +173,10 @@ fn get_syntax_diagnostics(
 fn get_text_range(ptrs: &Ptrs, ast_root: &Root, id: Id) -> TextRange {
   let root = ast_root.syntax().clone();
   match id {
-    Id::Expr(id) => ptrs.expr_back[id].to_node(&root).syntax().text_range(),
-    Id::Ty(id) => ptrs.ty_back[id].to_node(&root).syntax().text_range(),
-    Id::Stmt(id) => ptrs.stmt_back[id].to_node(&root).syntax().text_range(),
-    Id::Simp(id) => ptrs.simp_back[id].to_node(&root).syntax().text_range(),
-    Id::Item(id) => ptrs.item_back[id].to_node(&root).syntax().text_range(),
+    Id::Expr(id) => ptrs.expr[id].to_node(&root).syntax().text_range(),
+    Id::Ty(id) => ptrs.ty[id].to_node(&root).syntax().text_range(),
+    Id::Stmt(id) => ptrs.stmt[id].to_node(&root).syntax().text_range(),
+    Id::Simp(id) => ptrs.simp[id].to_node(&root).syntax().text_range(),
+    Id::Item(id) => ptrs.item[id].to_node(&root).syntax().text_range(),
   }
 }
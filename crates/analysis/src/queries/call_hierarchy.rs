@@ -0,0 +1,202 @@
+use crate::db::{Db, SyntaxData};
+use crate::types::{Call, FnItem, Location};
+use crate::util::get_token;
+use lower::Ptrs;
+use rustc_hash::FxHashMap;
+use syntax::ast::{AstNode as _, CallExpr, Item, Root as AstRoot};
+use syntax::rowan::TextRange;
+use syntax::SyntaxKind;
+use text_pos::{Position, Range};
+use unwrap_or::unwrap_or;
+use uri_db::{Uri, UriId};
+
+/// A call to a function from inside another function.
+#[derive(Debug)]
+pub(crate) struct CallSite {
+  /// The function containing the call.
+  pub(crate) caller: hir::ItemId,
+  /// The name of the called function.
+  pub(crate) callee: hir::Name,
+  /// The range of the name of the called function.
+  pub(crate) range: TextRange,
+}
+
+/// Returns every call in the file, each with its containing function.
+pub(crate) fn index(
+  ast_root: &AstRoot,
+  hir_root: &hir::Root,
+  ptrs: &Ptrs,
+) -> Vec<CallSite> {
+  hir_root
+    .arenas
+    .expr
+    .iter()
+    .filter_map(|(expr, data)| {
+      let callee = match *data {
+        hir::Expr::Call(ref name, _) => name.clone(),
+        _ => return None,
+      };
+      let node = ptrs.expr[expr].to_node(ast_root.syntax());
+      let range = CallExpr::cast(node.syntax().clone())?.ident()?.text_range();
+      let item = node.syntax().ancestors().find_map(Item::cast)?;
+      let caller = ptrs.item.get(&item)?;
+      matches!(hir_root.arenas.item[caller], hir::Item::Fn(..)).then(|| {
+        CallSite {
+          caller,
+          callee,
+          range,
+        }
+      })
+    })
+    .collect()
+}
+
+pub(crate) fn calls(db: &Db) -> Vec<Call> {
+  let mut ret = Vec::new();
+  for &uri in db.ordering().ids.iter() {
+    let syntax_data = db.syntax_data(uri);
+    for call in syntax_data.calls.iter() {
+      let caller = unwrap_or!(get_fn_item(db, uri, call.caller), continue);
+      let range = unwrap_or!(syntax_data.positions.range(call.range), continue);
+      ret.push(Call {
+        caller,
+        callee: call.callee.to_string(),
+        location: Location {
+          uri: db.uris[uri].clone(),
+          range,
+        },
+      });
+    }
+  }
+  ret
+}
+
+pub(crate) fn prepare(db: &Db, uri: &Uri, pos: Position) -> Option<FnItem> {
+  let id = db.uris.get_id(uri)?;
+  let syntax_data = db.syntax_data(id);
+  let tok = get_token(&syntax_data, pos)?;
+  if tok.kind() != SyntaxKind::Ident {
+    return None;
+  }
+  let parent = tok.parent()?;
+  match parent.kind() {
+    SyntaxKind::FnItem => {
+      let item = Item::cast(parent)?;
+      let item = syntax_data.ptrs.item.get(&item)?;
+      get_fn_item(db, id, item)
+    }
+    SyntaxKind::CallExpr => {
+      let semantic_data = db.semantic_data(id)?;
+      let (uri, item) = semantic_data.env.fns.get(tok.text())?.id()?;
+      get_fn_item(db, uri, item)
+    }
+    _ => None,
+  }
+}
+
+pub(crate) fn incoming(db: &Db, item: &FnItem) -> Vec<(FnItem, Vec<Range>)> {
+  let id = unwrap_or!(item_id(db, item), return Vec::new());
+  group(db, |uri, _, call| {
+    // another fn with the same name, e.g. in an unrelated program, is not this
+    // one.
+    let callee = db.semantic_data(uri)?.env.fns.get(&call.callee)?.id();
+    (callee == Some(id)).then_some((uri, call.caller))
+  })
+}
+
+pub(crate) fn outgoing(db: &Db, item: &FnItem) -> Vec<(FnItem, Vec<Range>)> {
+  let id = unwrap_or!(item_id(db, item), return Vec::new());
+  group(db, |uri, _, call| {
+    if (uri, call.caller) != id {
+      return None;
+    }
+    // std lib fns have no ID, so they won't appear.
+    db.semantic_data(uri)?.env.fns.get(&call.callee)?.id()
+  })
+}
+
+/// Groups the calls for which `f` returns `Some` by the fn `f` returned,
+/// keeping track of the ranges of the calls. Those ranges are always in the
+/// file containing the call.
+fn group<F>(db: &Db, mut f: F) -> Vec<(FnItem, Vec<Range>)>
+where
+  F: FnMut(UriId, &SyntaxData, &CallSite) -> Option<(UriId, hir::ItemId)>,
+{
+  let mut order = Vec::<(UriId, hir::ItemId)>::new();
+  let mut ranges = FxHashMap::<(UriId, hir::ItemId), Vec<Range>>::default();
+  for &uri in db.ordering().ids.iter() {
+    let syntax_data = db.syntax_data(uri);
+    for call in syntax_data.calls.iter() {
+      let key = unwrap_or!(f(uri, &syntax_data, call), continue);
+      let range = unwrap_or!(syntax_data.positions.range(call.range), continue);
+      ranges
+        .entry(key)
+        .or_insert_with(|| {
+          order.push(key);
+          Vec::new()
+        })
+        .push(range);
+    }
+  }
+  order
+    .into_iter()
+    .filter_map(|key| {
+      let item = get_fn_item(db, key.0, key.1)?;
+      Some((item, ranges.remove(&key)?))
+    })
+    .collect()
+}
+
+/// Returns the fn in the item's file whose name is at the item's name range.
+fn item_id(db: &Db, item: &FnItem) -> Option<(UriId, hir::ItemId)> {
+  let uri = db.uris.get_id(&item.uri)?;
+  let syntax_data = db.syntax_data(uri);
+  let ret = syntax_data
+    .hir_root
+    .arenas
+    .item
+    .iter()
+    .find_map(|(id, data)| {
+      match *data {
+        hir::Item::Fn(ref name, ..) if name == item.name.as_str() => {}
+        _ => return None,
+      }
+      let node =
+        syntax_data.ptrs.item[id].to_node(syntax_data.ast_root.syntax());
+      let name_range = match node {
+        Item::FnItem(ref item) => item.ident()?.text_range(),
+        _ => return None,
+      };
+      let name_range = syntax_data.positions.range(name_range)?;
+      (name_range == item.name_range).then_some((uri, id))
+    });
+  ret
+}
+
+fn get_fn_item(db: &Db, uri: UriId, item: hir::ItemId) -> Option<FnItem> {
+  let syntax_data = db.syntax_data(uri);
+  let name = match syntax_data.hir_root.arenas.item[item] {
+    hir::Item::Fn(ref name, ..) => name,
+    _ => return None,
+  };
+  let node = syntax_data.ptrs.item[item].to_node(syntax_data.ast_root.syntax());
+  let name_range = match node {
+    Item::FnItem(ref item) => item.ident()?.text_range(),
+    _ => return None,
+  };
+  let semantic_data = db.semantic_data(uri)?;
+  let signature = semantic_data
+    .env
+    .fns
+    .get(name)?
+    .val()
+    .display(name, &db.tys())
+    .to_string();
+  Some(FnItem {
+    name: name.to_string(),
+    signature,
+    uri: db.uris[uri].clone(),
+    range: syntax_data.positions.range(node.syntax().text_range())?,
+    name_range: syntax_data.positions.range(name_range)?,
+  })
+}
@@ -0,0 +1,88 @@
+use crate::db::{Db, SyntaxData};
+use crate::types::{Completion, CompletionKind};
+use crate::util::{get_token, var_decls};
+use rustc_hash::FxHashSet;
+use statics::{Ambiguous, Env};
+use syntax::ast::{AstNode as _, Param, Simp, SimpOpt, SimpStmt};
+use syntax::SyntaxNode;
+use text_pos::Position;
+use uri_db::Uri;
+
+pub(crate) fn get(
+  db: &Db,
+  uri: &Uri,
+  pos: Position,
+) -> Option<Vec<Completion>> {
+  let id = db.uris.get_id(uri)?;
+  let syntax_data = db.syntax_data(id);
+  let semantic_data = db.semantic_data(id)?;
+  let env = &semantic_data.env;
+  let tys = db.tys();
+  let mut ret = Vec::new();
+  if let Some(tok) = get_token(&syntax_data, pos) {
+    let mut seen = FxHashSet::default();
+    for (node, name) in var_decls(&syntax_data, env, tok.into()) {
+      if !seen.insert(name.text().to_owned()) {
+        continue;
+      }
+      let detail = get_var_ty(&syntax_data, env, &node)
+        .map(|ty| format!("{} {}", ty.display(&tys), name.text()));
+      ret.push(Completion {
+        label: name.text().to_owned(),
+        kind: CompletionKind::Var,
+        detail,
+        doc: None,
+      });
+    }
+  }
+  for (name, data) in env.fns.iter() {
+    ret.push(Completion {
+      label: name.to_string(),
+      kind: CompletionKind::Fn,
+      detail: Some(data.val().display(name, &tys).to_string()),
+      doc: data.doc().map(ToOwned::to_owned),
+    });
+  }
+  for (name, data) in env.type_defs.iter() {
+    ret.push(Completion {
+      label: name.to_string(),
+      kind: CompletionKind::TypeDef,
+      detail: Some(format!("typedef {} {}", data.val().display(&tys), name)),
+      doc: data.doc().map(ToOwned::to_owned),
+    });
+  }
+  for (name, data) in env.structs.iter() {
+    ret.push(Completion {
+      label: name.to_string(),
+      kind: CompletionKind::Struct,
+      detail: Some(format!("struct {}", name)),
+      doc: data.doc().map(ToOwned::to_owned),
+    });
+  }
+  Some(ret)
+}
+
+/// `node` should be a node that declares a variable.
+fn get_var_ty(
+  syntax_data: &SyntaxData,
+  env: &Env,
+  node: &SyntaxNode,
+) -> Option<statics::Ty> {
+  let simp = SimpStmt::cast(node.clone())
+    .and_then(|x| x.simp())
+    .or_else(|| SimpOpt::cast(node.clone()).and_then(|x| x.simp()));
+  let ty = match simp {
+    Some(Simp::DeclSimp(simp)) => simp.ty()?,
+    Some(simp @ Simp::AmbiguousSimp(_)) => {
+      let simp = syntax_data.ptrs.simp.get(&simp)?;
+      return match *env.ambiguous_simps.get(simp)? {
+        Ambiguous::Decl(ty) => Some(ty),
+        Ambiguous::Mul => None,
+      };
+    }
+    Some(_) => return None,
+    None => Param::cast(node.clone())?.ty()?,
+  };
+  let ty = syntax_data.ptrs.ty.get(&ty)?;
+  env.ty_tys.get(ty).copied()
+}
@@ -0,0 +1,52 @@
+use crate::db::Db;
+use crate::types::{FoldingRange, FoldingRangeKind};
+use syntax::ast::AstNode as _;
+use syntax::rowan::TextRange;
+use syntax::{SyntaxKind, SyntaxNode};
+use uri_db::Uri;
+
+pub(crate) fn get(db: &Db, uri: &Uri) -> Option<Vec<FoldingRange>> {
+  let id = db.uris.get_id(uri)?;
+  let syntax_data = db.syntax_data(id);
+  let root = syntax_data.ast_root.syntax();
+  let mut text_ranges = Vec::new();
+  for elem in root.descendants_with_tokens() {
+    let kind = match elem.kind() {
+      SyntaxKind::BlockStmt | SyntaxKind::Fields => FoldingRangeKind::Region,
+      SyntaxKind::BlockComment => FoldingRangeKind::Comment,
+      _ => continue,
+    };
+    text_ranges.push((elem.text_range(), kind));
+  }
+  text_ranges
+    .extend(use_runs(root).map(|range| (range, FoldingRangeKind::Imports)));
+  let ret = text_ranges
+    .into_iter()
+    .filter_map(|(text_range, kind)| {
+      let range = syntax_data.positions.range(text_range)?;
+      (range.start.line < range.end.line)
+        .then_some(FoldingRange { range, kind })
+    })
+    .collect();
+  Some(ret)
+}
+
+/// Returns the ranges of consecutive runs of `#use` pragmas.
+fn use_runs(root: &SyntaxNode) -> impl Iterator<Item = TextRange> {
+  let mut ret = Vec::new();
+  let mut cur: Option<TextRange> = None;
+  for node in root.children() {
+    let is_use = node.kind() == SyntaxKind::PragmaItem
+      && node.first_token().is_some_and(|tok| {
+        tok.kind() == SyntaxKind::Pragma && tok.text().starts_with("#use")
+      });
+    if is_use {
+      let range = node.text_range();
+      cur = Some(cur.map_or(range, |cur| cur.cover(range)));
+    } else if let Some(cur) = cur.take() {
+      ret.push(cur);
+    }
+  }
+  ret.extend(cur);
+  ret.into_iter()
+}
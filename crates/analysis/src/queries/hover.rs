@@ -1,55 +1,156 @@
-use crate::db::Db;
+use crate::db::{Db, SemanticData, SyntaxData};
 use crate::types::{CodeBlock, Hover};
 use crate::util::get_token;
-use syntax::ast::{AstNode as _, AstPtr, Expr, Ty};
+use statics::TyDb;
+use std::fmt::Write as _;
+use std_lib::Lib;
+use syntax::ast::{AstNode as _, Expr, Field, Param, StructItem, Ty};
+use syntax::rowan::TextRange;
+use syntax::{SyntaxKind, SyntaxToken};
 use text_pos::Position;
 use uri_db::Uri;
 
 pub(crate) fn get(db: &Db, uri: &Uri, pos: Position) -> Option<Hover> {
-  let done = db.kind.done()?;
   let id = db.uris.get_id(uri)?;
-  let syntax_data = &db.syntax_data[&id];
-  let semantic_data = &done.semantic_data[&id];
-  let mut node = get_token(syntax_data, pos)?.parent()?;
+  let syntax_data = db.syntax_data(id);
+  let semantic_data = db.semantic_data(id)?;
+  let env = &semantic_data.env;
+  let tys = db.tys();
+  let tok = get_token(&syntax_data, pos)?;
+  if let Some(ret) = get_decl(db, &tys, &syntax_data, &semantic_data, &tok) {
+    return Some(ret);
+  }
+  let mut node = tok.parent()?;
   loop {
     if let Some(expr_node) = Expr::cast(node.clone()) {
-      let expr = *syntax_data.ptrs.expr.get(&AstPtr::new(&expr_node))?;
-      let contents = match syntax_data.hir_root.arenas.expr[expr] {
-        hir::Expr::Call(ref name, _) => semantic_data
-          .env
-          .fns
-          .get(name)?
-          .val()
-          .display(name, &done.cx.tys)
-          .to_string(),
-        _ => semantic_data
-          .env
-          .expr_tys
-          .get(expr)?
-          .display(&done.cx.tys)
-          .to_string(),
+      let expr = syntax_data.ptrs.expr.get(&expr_node)?;
+      let (contents, doc) = match syntax_data.hir_root.arenas.expr[expr] {
+        hir::Expr::Call(ref name, _) => {
+          let data = env.fns.get(name)?;
+          let contents = data.val().display(name, &tys).to_string();
+          (contents, data.doc())
+        }
+        _ => {
+          let ty = env.expr_tys.get(expr)?;
+          (ty.display(&tys).to_string(), None)
+        }
       };
       let text_range = expr_node.syntax().text_range();
-      return Some(Hover {
-        contents: CodeBlock::new(contents),
-        range: syntax_data.positions.range(text_range)?,
-      });
+      return mk_hover(&syntax_data, contents, doc, text_range);
     }
     if let Some(ty_node) = Ty::cast(node.clone()) {
-      let ty = *syntax_data.ptrs.ty.get(&AstPtr::new(&ty_node))?;
+      let ty = syntax_data.ptrs.ty.get(&ty_node)?;
+      let contents = env.ty_tys.get(ty)?.display(&tys).to_string();
+      let doc = match syntax_data.hir_root.arenas.ty[ty] {
+        hir::Ty::Struct(ref name) => {
+          env.structs.get(name).and_then(|x| x.doc())
+        }
+        hir::Ty::Name(ref name) => {
+          env.type_defs.get(name).and_then(|x| x.doc())
+        }
+        _ => None,
+      };
       let text_range = ty_node.syntax().text_range();
-      return Some(Hover {
-        contents: CodeBlock::new(
-          semantic_data
-            .env
-            .ty_tys
-            .get(ty)?
-            .display(&done.cx.tys)
-            .to_string(),
-        ),
-        range: syntax_data.positions.range(text_range)?,
-      });
+      return mk_hover(&syntax_data, contents, doc, text_range);
     }
     node = node.parent()?;
   }
 }
+
+/// Returns hover information for tokens that are part of a declaration of
+/// something, like a fn name in its definition, rather than an expression or
+/// type.
+fn get_decl(
+  db: &Db,
+  tys: &TyDb,
+  syntax_data: &SyntaxData,
+  semantic_data: &SemanticData,
+  tok: &SyntaxToken,
+) -> Option<Hover> {
+  let env = &semantic_data.env;
+  let mut text_range = tok.text_range();
+  let contents = match tok.kind() {
+    SyntaxKind::Pragma => {
+      let u = syntax_data
+        .uses
+        .iter()
+        .find(|u| u.range.intersect(text_range).is_some())?;
+      let lib: Lib = match u.kind {
+        syntax::UseKind::Lib => u.path.parse().ok()?,
+        syntax::UseKind::Local => return None,
+      };
+      text_range = u.range;
+      // the semantic data of this file was computed, so so was the env of every
+      // library it uses.
+      let env = db.std_lib.get_computed(lib)?;
+      let mut fns: Vec<_> = env.fns.iter().collect();
+      fns.sort_unstable_by_key(|&(name, _)| name);
+      let mut ret = format!("#use <{}>", u.path);
+      for (name, data) in fns {
+        write!(ret, "\n{};", data.val().display(name, tys)).unwrap();
+      }
+      ret
+    }
+    SyntaxKind::Ident => {
+      let parent = tok.parent()?;
+      let ty_node = match parent.kind() {
+        SyntaxKind::FnItem => {
+          let (name, data) = env.fns.get_key_value(tok.text())?;
+          let contents = data.val().display(name, tys).to_string();
+          return mk_hover(syntax_data, contents, data.doc(), text_range);
+        }
+        SyntaxKind::StructItem => {
+          let item = StructItem::cast(parent)?;
+          let mut ret = format!("struct {}", tok.text());
+          if let Some(fields) = item.fields() {
+            ret.push_str(" {");
+            for field in fields.fields() {
+              let ty = get_ty(syntax_data, semantic_data, field.ty()?)?;
+              let name = field.ident()?;
+              write!(ret, "\n  {} {};", ty.display(tys), name.text()).unwrap();
+            }
+            ret.push_str("\n}");
+          }
+          ret.push(';');
+          let doc = env.structs.get(tok.text()).and_then(|x| x.doc());
+          return mk_hover(syntax_data, ret, doc, text_range);
+        }
+        SyntaxKind::TypedefItem => {
+          let data = env.type_defs.get(tok.text())?;
+          let contents =
+            format!("typedef {} {};", data.val().display(tys), tok.text());
+          return mk_hover(syntax_data, contents, data.doc(), text_range);
+        }
+        SyntaxKind::Param => Param::cast(parent)?.ty()?,
+        SyntaxKind::Field => Field::cast(parent)?.ty()?,
+        _ => return None,
+      };
+      let ty = get_ty(syntax_data, semantic_data, ty_node)?;
+      format!("{} {}", ty.display(tys), tok.text())
+    }
+    _ => return None,
+  };
+  mk_hover(syntax_data, contents, None, text_range)
+}
+
+fn get_ty(
+  syntax_data: &SyntaxData,
+  semantic_data: &SemanticData,
+  ty: Ty,
+) -> Option<statics::Ty> {
+  let ty = syntax_data.ptrs.ty.get(&ty)?;
+  semantic_data.env.ty_tys.get(ty).copied()
+}
+
+fn mk_hover(
+  syntax_data: &SyntaxData,
+  contents: String,
+  doc: Option<&str>,
+  text_range: TextRange,
+) -> Option<Hover> {
+  Some(Hover {
+    contents: CodeBlock::new(contents),
+    doc: doc.map(ToOwned::to_owned),
+    range: syntax_data.positions.range(text_range)?,
+  })
+}
@@ -0,0 +1,325 @@
+//! Pretty-printing what we know about a file, for debugging the parser and
+//! lowering, and for showing how the compiler sees the code.
+
+use crate::db::Db;
+use hir::la_arena::Idx;
+use hir::{
+  AssignOp, BinOp, Expr, IncDec, Item, MathOp, Root, Simp, Stmt, Ty, UnOp,
+};
+use statics::{Ambiguous, Env, TyDb};
+use std::fmt::{self, Write as _};
+use syntax::ast::AstNode as _;
+use uri_db::Uri;
+
+pub(crate) fn syntax_tree(db: &Db, uri: &Uri) -> Option<String> {
+  let id = db.uris.get_id(uri)?;
+  let syntax_data = db.syntax_data(id);
+  Some(format!("{:#?}", syntax_data.ast_root.syntax()))
+}
+
+pub(crate) fn tokens(db: &Db, uri: &Uri) -> Option<String> {
+  let id = db.uris.get_id(uri)?;
+  let syntax_data = db.syntax_data(id);
+  let mut ret = String::new();
+  for tok in syntax_data
+    .ast_root
+    .syntax()
+    .descendants_with_tokens()
+    .filter_map(|elem| elem.into_token())
+  {
+    let range = syntax_data.positions.range(tok.text_range())?;
+    writeln!(
+      ret,
+      "{:?}@{}:{}..{}:{} {:?}",
+      tok.kind(),
+      range.start.line,
+      range.start.character,
+      range.end.line,
+      range.end.character,
+      tok.text()
+    )
+    .unwrap();
+  }
+  Some(ret)
+}
+
+/// Each node is on its own line, like `expr3: int = expr1 + expr2`, with its
+/// type if it has one, then what it is in terms of the other nodes.
+pub(crate) fn hir(db: &Db, uri: &Uri) -> Option<String> {
+  let id = db.uris.get_id(uri)?;
+  let syntax_data = db.syntax_data(id);
+  // no types if the file is in a use cycle.
+  let semantic_data = db.semantic_data(id);
+  let tys = db.tys();
+  let cx = Cx {
+    root: &syntax_data.hir_root,
+    env: semantic_data.as_ref().map(|sd| &sd.env),
+    tys: &tys,
+  };
+  let mut ret = String::new();
+  cx.write(&mut ret).unwrap();
+  Some(ret)
+}
+
+struct Cx<'a> {
+  root: &'a Root,
+  env: Option<&'a Env>,
+  tys: &'a TyDb,
+}
+
+impl Cx<'_> {
+  fn write(&self, f: &mut String) -> fmt::Result {
+    let arenas = &self.root.arenas;
+    writeln!(f, "# items")?;
+    for (id, item) in arenas.item.iter() {
+      write!(f, "{}", Id("item", id))?;
+      if let Some(doc) = self.root.docs.get(id) {
+        write!(f, ": {:?}", doc)?;
+      }
+      write!(f, " = ")?;
+      self.item(f, item)?;
+      writeln!(f)?;
+    }
+    writeln!(f, "\n# statements")?;
+    for (id, stmt) in arenas.stmt.iter() {
+      write!(f, "{} = ", Id("stmt", id))?;
+      self.stmt(f, stmt)?;
+      writeln!(f)?;
+    }
+    writeln!(f, "\n# simple statements")?;
+    for (id, simp) in arenas.simp.iter() {
+      write!(f, "{}", Id("simp", id))?;
+      match self.env.and_then(|env| env.ambiguous_simps.get(id)) {
+        None => {}
+        Some(Ambiguous::Mul) => write!(f, ": mul")?,
+        Some(Ambiguous::Decl(ty)) => {
+          write!(f, ": decl {}", ty.display(self.tys))?
+        }
+      }
+      write!(f, " = ")?;
+      self.simp(f, simp)?;
+      writeln!(f)?;
+    }
+    writeln!(f, "\n# expressions")?;
+    for (id, expr) in arenas.expr.iter() {
+      write!(f, "{}", Id("expr", id))?;
+      if let Some(ty) = self.env.and_then(|env| env.expr_tys.get(id)) {
+        write!(f, ": {}", ty.display(self.tys))?;
+      }
+      write!(f, " = ")?;
+      self.expr(f, expr)?;
+      writeln!(f)?;
+    }
+    writeln!(f, "\n# types")?;
+    for (id, ty) in arenas.ty.iter() {
+      write!(f, "{}", Id("ty", id))?;
+      if let Some(ty) = self.env.and_then(|env| env.ty_tys.get(id)) {
+        write!(f, ": {}", ty.display(self.tys))?;
+      }
+      write!(f, " = ")?;
+      self.ty(f, ty)?;
+      writeln!(f)?;
+    }
+    Ok(())
+  }
+
+  fn item(&self, f: &mut String, item: &Item) -> fmt::Result {
+    match item {
+      Item::Fn(name, params, ret_ty, body) => {
+        write!(f, "{} {}(", Id("ty", *ret_ty), name)?;
+        for (idx, param) in params.iter().enumerate() {
+          if idx != 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{} {}", Id("ty", param.ty), param.name)?;
+        }
+        write!(f, ")")?;
+        match body {
+          None => write!(f, ";"),
+          Some(body) => write!(f, " {}", Id("stmt", *body)),
+        }
+      }
+      Item::Struct(name, fields) => {
+        write!(f, "struct {} {{", name)?;
+        for field in fields {
+          write!(f, " {} {};", Id("ty", field.ty), field.name)?;
+        }
+        write!(f, " }}")
+      }
+      Item::TypeDef(name, ty) => {
+        write!(f, "typedef {} {}", Id("ty", *ty), name)
+      }
+    }
+  }
+
+  fn stmt(&self, f: &mut String, stmt: &Stmt) -> fmt::Result {
+    match *stmt {
+      Stmt::None => write!(f, "<none>"),
+      Stmt::Simp(simp) => write!(f, "{}", Id("simp", simp)),
+      Stmt::If(cond, yes, no) => {
+        write!(f, "if ({}) {}", Id("expr", cond), Id("stmt", yes))?;
+        match no {
+          None => Ok(()),
+          Some(no) => write!(f, " else {}", Id("stmt", no)),
+        }
+      }
+      Stmt::While(cond, body) => {
+        write!(f, "while ({}) {}", Id("expr", cond), Id("stmt", body))
+      }
+      Stmt::For(init, cond, step, body) => {
+        write!(f, "for (")?;
+        if let Some(init) = init {
+          write!(f, "{}", Id("simp", init))?;
+        }
+        write!(f, "; {};", Id("expr", cond))?;
+        if let Some(step) = step {
+          write!(f, " {}", Id("simp", step))?;
+        }
+        write!(f, ") {}", Id("stmt", body))
+      }
+      Stmt::Return(None) => write!(f, "return"),
+      Stmt::Return(Some(expr)) => write!(f, "return {}", Id("expr", expr)),
+      Stmt::Block(ref stmts) => {
+        write!(f, "{{")?;
+        for &stmt in stmts {
+          write!(f, " {}", Id("stmt", stmt))?;
+        }
+        write!(f, " }}")
+      }
+      Stmt::Assert(expr) => write!(f, "assert({})", Id("expr", expr)),
+      Stmt::Error(expr) => write!(f, "error({})", Id("expr", expr)),
+      Stmt::Break => write!(f, "break"),
+      Stmt::Continue => write!(f, "continue"),
+    }
+  }
+
+  fn simp(&self, f: &mut String, simp: &Simp) -> fmt::Result {
+    match *simp {
+      Simp::Assign(lhs, op, rhs) => {
+        let op = match op {
+          AssignOp::Eq => "",
+          AssignOp::OpEq(op) => math_op(op),
+        };
+        write!(f, "{} {}= {}", Id("expr", lhs), op, Id("expr", rhs))
+      }
+      Simp::IncDec(expr, inc_dec) => {
+        let op = match inc_dec {
+          IncDec::Inc => "++",
+          IncDec::Dec => "--",
+        };
+        write!(f, "{}{}", Id("expr", expr), op)
+      }
+      Simp::Decl(ref name, ty, init) => {
+        write!(f, "{} {}", Id("ty", ty), name)?;
+        match init {
+          None => Ok(()),
+          Some(init) => write!(f, " = {}", Id("expr", init)),
+        }
+      }
+      Simp::Expr(expr) => write!(f, "{}", Id("expr", expr)),
+      Simp::Ambiguous(ref lhs, ref rhs) => write!(f, "{} * {}", lhs, rhs),
+    }
+  }
+
+  fn expr(&self, f: &mut String, expr: &Expr) -> fmt::Result {
+    match *expr {
+      Expr::None => write!(f, "<none>"),
+      Expr::Int => write!(f, "<int>"),
+      Expr::Bool => write!(f, "<bool>"),
+      Expr::Char => write!(f, "<char>"),
+      Expr::String => write!(f, "<string>"),
+      Expr::Null => write!(f, "NULL"),
+      Expr::Name(ref name) => write!(f, "{}", name),
+      Expr::BinOp(lhs, op, rhs) => {
+        let op = match op {
+          BinOp::Math(op) => math_op(op),
+          BinOp::Eq => "==",
+          BinOp::Neq => "!=",
+          BinOp::Lt => "<",
+          BinOp::LtEq => "<=",
+          BinOp::Gt => ">",
+          BinOp::GtEq => ">=",
+          BinOp::And => "&&",
+          BinOp::Or => "||",
+        };
+        write!(f, "{} {} {}", Id("expr", lhs), op, Id("expr", rhs))
+      }
+      Expr::UnOp(op, expr) => {
+        let op = match op {
+          UnOp::Not => "!",
+          UnOp::BitNot => "~",
+          UnOp::Neg => "-",
+          UnOp::Deref => "*",
+        };
+        write!(f, "{}{}", op, Id("expr", expr))
+      }
+      Expr::Ternary(cond, yes, no) => write!(
+        f,
+        "{} ? {} : {}",
+        Id("expr", cond),
+        Id("expr", yes),
+        Id("expr", no)
+      ),
+      Expr::Call(ref name, ref args) => {
+        write!(f, "{}(", name)?;
+        for (idx, &arg) in args.iter().enumerate() {
+          if idx != 0 {
+            write!(f, ", ")?;
+          }
+          write!(f, "{}", Id("expr", arg))?;
+        }
+        write!(f, ")")
+      }
+      Expr::FieldGet(expr, ref name) => {
+        write!(f, "{}.{}", Id("expr", expr), name)
+      }
+      Expr::Subscript(array, idx) => {
+        write!(f, "{}[{}]", Id("expr", array), Id("expr", idx))
+      }
+      Expr::Alloc(ty) => write!(f, "alloc({})", Id("ty", ty)),
+      Expr::AllocArray(ty, len) => {
+        write!(f, "alloc_array({}, {})", Id("ty", ty), Id("expr", len))
+      }
+    }
+  }
+
+  fn ty(&self, f: &mut String, ty: &Ty) -> fmt::Result {
+    match *ty {
+      Ty::None => write!(f, "<none>"),
+      Ty::Any => write!(f, "<any>"),
+      Ty::Int => write!(f, "int"),
+      Ty::Bool => write!(f, "bool"),
+      Ty::Char => write!(f, "char"),
+      Ty::String => write!(f, "string"),
+      Ty::Void => write!(f, "void"),
+      Ty::Ptr(ty) => write!(f, "{}*", Id("ty", ty)),
+      Ty::Array(ty) => write!(f, "{}[]", Id("ty", ty)),
+      Ty::Struct(ref name) => write!(f, "struct {}", name),
+      Ty::Name(ref name) => write!(f, "{}", name),
+    }
+  }
+}
+
+fn math_op(op: MathOp) -> &'static str {
+  match op {
+    MathOp::Add => "+",
+    MathOp::Sub => "-",
+    MathOp::Mul => "*",
+    MathOp::Div => "/",
+    MathOp::Mod => "%",
+    MathOp::Shl => "<<",
+    MathOp::Shr => ">>",
+    MathOp::BitAnd => "&",
+    MathOp::BitXor => "^",
+    MathOp::BitOr => "|",
+  }
+}
+
+/// A node of an arena, like `expr3`.
+struct Id<T>(&'static str, Idx<T>);
+
+impl<T> fmt::Display for Id<T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}{}", self.0, u32::from(self.1.into_raw()))
+  }
+}
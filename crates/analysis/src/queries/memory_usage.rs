@@ -0,0 +1,99 @@
+//! Estimating how much memory the `Db` uses.
+//!
+//! The estimates are rough: we count the elements of each collection, but not
+//! e.g. allocator overhead. Memoized data that is shared between files, like
+//! the types, is not counted.
+
+use crate::db::{Db, Memos, SemanticData, SyntaxData};
+use crate::types::MemoryUsage;
+use rustc_hash::FxHashMap;
+use statics::{Ambiguous, Ty};
+use std::mem::size_of;
+use std::rc::Rc;
+use syntax::ast::AstNode as _;
+use syntax::rowan::NodeOrToken;
+
+/// The approximate size of the header of a green node or token.
+const GREEN_HEADER: usize = 16;
+/// The approximate size of each child of a green node.
+const GREEN_CHILD: usize = 16;
+
+pub(crate) fn get(db: &Db) -> MemoryUsage {
+  let mut ret = MemoryUsage {
+    files: db.files.len(),
+    ..MemoryUsage::default()
+  };
+  for file in db.files.values() {
+    ret.contents += file.contents.capacity();
+    ret.contents += file.disk.as_ref().map_or(0, String::capacity);
+    if let Some(syntax_data) = file.syntax.borrow().as_ref() {
+      add_syntax(&mut ret, syntax_data);
+    }
+  }
+  let target_memos = db.targets.iter().flat_map(|t| t.memos.iter());
+  let memos = db.files.iter().map(|(id, file)| (id, &file.memos));
+  for (id, memos) in memos.chain(target_memos) {
+    add_memos(&mut ret, &db.files[id].syntax.borrow(), memos);
+  }
+  ret
+}
+
+fn add_memos(
+  ret: &mut MemoryUsage,
+  syntax_data: &Option<Rc<SyntaxData>>,
+  memos: &Memos,
+) {
+  if let (Some(syntax_data), Some(memo)) =
+    (syntax_data, memos.semantic.borrow().as_ref())
+  {
+    ret.envs += env_size(syntax_data, &memo.value);
+  }
+  if let Some(memo) = memos.diagnostics.borrow().as_ref() {
+    ret.diagnostics += memo
+      .value
+      .iter()
+      .map(|d| size_of::<crate::Diagnostic>() + d.message.capacity())
+      .sum::<usize>();
+  }
+}
+
+fn add_syntax(ret: &mut MemoryUsage, syntax_data: &SyntaxData) {
+  ret.positions += syntax_data.positions.heap_size();
+  // identical tokens and small nodes may be shared, so this is an upper bound.
+  for elem in syntax_data.ast_root.syntax().descendants_with_tokens() {
+    ret.syntax_trees += match elem {
+      NodeOrToken::Node(node) => {
+        GREEN_HEADER + GREEN_CHILD * node.children_with_tokens().count()
+      }
+      NodeOrToken::Token(tok) => GREEN_HEADER + tok.text().len(),
+    };
+  }
+  let root = &syntax_data.hir_root;
+  let arenas = &root.arenas;
+  ret.hir += arenas.item.len() * size_of::<hir::Item>()
+    + arenas.expr.len() * size_of::<hir::Expr>()
+    + arenas.ty.len() * size_of::<hir::Ty>()
+    + arenas.stmt.len() * size_of::<hir::Stmt>()
+    + arenas.simp.len() * size_of::<hir::Simp>()
+    + root.items.capacity() * size_of::<hir::ItemId>()
+    + root.docs.values().map(String::capacity).sum::<usize>();
+  ret.ptrs += syntax_data.ptrs.heap_size();
+}
+
+fn env_size(syntax_data: &SyntaxData, semantic_data: &SemanticData) -> usize {
+  let env = &semantic_data.env;
+  let arenas = &syntax_data.hir_root.arenas;
+  // the envs include everything imported from other files, so these are
+  // usually the biggest part.
+  map_size(&env.fns)
+    + map_size(&env.structs)
+    + map_size(&env.type_defs)
+    + arenas.expr.len() * size_of::<Option<Ty>>()
+    + arenas.ty.len() * size_of::<Option<Ty>>()
+    + arenas.simp.len() * size_of::<Option<Ambiguous>>()
+}
+
+fn map_size<K, V>(map: &FxHashMap<K, V>) -> usize {
+  // each entry also has a control byte.
+  map.capacity() * (1 + size_of::<(K, V)>())
+}
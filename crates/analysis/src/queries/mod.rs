@@ -1,3 +1,10 @@
 pub(crate) mod all_diagnostics;
+pub(crate) mod call_hierarchy;
+pub(crate) mod completions;
+pub(crate) mod folding_ranges;
 pub(crate) mod go_to_def;
 pub(crate) mod hover;
+pub(crate) mod inspect;
+pub(crate) mod memory_usage;
+pub(crate) mod selection_ranges;
+pub(crate) mod semantic_tokens;
@@ -0,0 +1,40 @@
+use crate::db::Db;
+use crate::db::SyntaxData;
+use crate::util::get_token;
+use text_pos::{Position, Range};
+use uri_db::Uri;
+
+pub(crate) fn get(
+  db: &Db,
+  uri: &Uri,
+  positions: &[Position],
+) -> Option<Vec<Vec<Range>>> {
+  let id = db.uris.get_id(uri)?;
+  let syntax_data = db.syntax_data(id);
+  let ret = positions
+    .iter()
+    .map(|&pos| {
+      // there must be one result per position, so if we can't get the ranges,
+      // use the empty range at the position.
+      ranges(&syntax_data, pos).unwrap_or_else(|| {
+        vec![Range {
+          start: pos,
+          end: pos,
+        }]
+      })
+    })
+    .collect();
+  Some(ret)
+}
+
+fn ranges(syntax_data: &SyntaxData, pos: Position) -> Option<Vec<Range>> {
+  let tok = get_token(syntax_data, pos)?;
+  let mut ret = vec![syntax_data.positions.range(tok.text_range())?];
+  for node in tok.parent()?.ancestors() {
+    let range = syntax_data.positions.range(node.text_range())?;
+    if ret.last() != Some(&range) {
+      ret.push(range);
+    }
+  }
+  Some(ret)
+}
@@ -0,0 +1,103 @@
+use crate::db::{Db, SemanticData, SyntaxData};
+use crate::types::{SemanticToken, SemanticTokenKind};
+use crate::util::get_var_def;
+use statics::Ambiguous;
+use syntax::ast::{AmbiguousSimp, AstNode as _, Simp};
+use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+use text_pos::Range;
+use uri_db::Uri;
+
+pub(crate) fn get(
+  db: &Db,
+  uri: &Uri,
+  range: Option<Range>,
+) -> Option<Vec<SemanticToken>> {
+  let id = db.uris.get_id(uri)?;
+  let syntax_data = db.syntax_data(id);
+  let semantic_data = db.semantic_data(id)?;
+  let root = syntax_data.ast_root.syntax();
+  let text_range = match range {
+    None => root.text_range(),
+    Some(range) => syntax_data.positions.text_range(range)?,
+  };
+  let ret = root
+    .descendants_with_tokens()
+    .filter_map(|elem| elem.into_token())
+    .filter(|tok| {
+      tok.kind() == SyntaxKind::Ident
+        && tok.text_range().start() < text_range.end()
+        && text_range.start() < tok.text_range().end()
+    })
+    .filter_map(|tok| {
+      let kind = get_kind(&syntax_data, &semantic_data, &tok)?;
+      let range = syntax_data.positions.range(tok.text_range())?;
+      Some(SemanticToken { range, kind })
+    })
+    .collect();
+  Some(ret)
+}
+
+fn get_kind(
+  syntax_data: &SyntaxData,
+  semantic_data: &SemanticData,
+  tok: &SyntaxToken,
+) -> Option<SemanticTokenKind> {
+  let parent = tok.parent()?;
+  let ret = match parent.kind() {
+    SyntaxKind::TypedefItem | SyntaxKind::IdentTy => SemanticTokenKind::TypeDef,
+    SyntaxKind::StructItem | SyntaxKind::StructTy => SemanticTokenKind::Struct,
+    SyntaxKind::FnItem => SemanticTokenKind::Fn,
+    SyntaxKind::CallExpr => {
+      // std lib fns are the only ones without an ID, save for the implicit
+      // declaration of `main`.
+      match semantic_data.env.fns.get(tok.text()) {
+        Some(data) if data.id().is_none() && tok.text() != "main" => {
+          SemanticTokenKind::LibFn
+        }
+        _ => SemanticTokenKind::Fn,
+      }
+    }
+    SyntaxKind::Param => SemanticTokenKind::Param,
+    SyntaxKind::Field
+    | SyntaxKind::FieldGetExpr
+    | SyntaxKind::DerefFieldGetExpr => SemanticTokenKind::Field,
+    SyntaxKind::DeclSimp => SemanticTokenKind::Local,
+    SyntaxKind::IdentExpr => {
+      get_var_kind(syntax_data, semantic_data, parent.parent()?, tok)?
+    }
+    SyntaxKind::AmbiguousSimp => {
+      let simp = Simp::cast(parent.clone())?;
+      let simp = syntax_data.ptrs.simp.get(&simp)?;
+      let is_lhs = AmbiguousSimp::cast(parent.clone())?.lhs()? == *tok;
+      match *semantic_data.env.ambiguous_simps.get(simp)? {
+        Ambiguous::Decl(_) => {
+          if is_lhs {
+            SemanticTokenKind::TypeDef
+          } else {
+            SemanticTokenKind::Local
+          }
+        }
+        Ambiguous::Mul => {
+          get_var_kind(syntax_data, semantic_data, parent.parent()?, tok)?
+        }
+      }
+    }
+    _ => return None,
+  };
+  Some(ret)
+}
+
+fn get_var_kind(
+  syntax_data: &SyntaxData,
+  semantic_data: &SemanticData,
+  node: SyntaxNode,
+  tok: &SyntaxToken,
+) -> Option<SemanticTokenKind> {
+  let def = get_var_def(syntax_data, &semantic_data.env, node, tok.text())?;
+  let ret = if def.kind() == SyntaxKind::Param {
+    SemanticTokenKind::Param
+  } else {
+    SemanticTokenKind::Local
+  };
+  Some(ret)
+}
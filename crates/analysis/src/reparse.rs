@@ -0,0 +1,224 @@
+//! Incremental reparsing.
+//!
+//! When an edit falls inside a single token, or inside a single block, we
+//! re-lex and re-parse only that token or block, and splice the result into the
+//! old tree. The rest of the syntax data, like the HIR, is then re-computed
+//! from the new tree.
+//!
+//! The edited token or block must lex and parse the same way it would have if
+//! we had lexed and parsed the whole file, so we are conservative: when in
+//! doubt, we give up, and the caller re-parses the whole file.
+
+use crate::db::SyntaxData;
+use crate::position_db::PositionDb;
+use syntax::ast::{AstNode as _, Root as AstRoot};
+use syntax::rowan::{GreenNode, GreenToken, NodeOrToken, TextRange, TextSize};
+use syntax::{SyntaxKind as SK, SyntaxNode};
+
+/// Applies the edit, which replaces the text in the range with the new text,
+/// to the syntax data.
+///
+/// If we can't reparse incrementally, returns the positions of the syntax data,
+/// updated for the edit.
+pub(crate) fn get(
+  mut syntax_data: SyntaxData,
+  range: TextRange,
+  text: &str,
+) -> Result<SyntaxData, PositionDb> {
+  syntax_data.positions.edit(range, text);
+  let reparsed = match token(&syntax_data, range, text)
+    .or_else(|| block(&syntax_data, range, text))
+  {
+    Some(x) => x,
+    None => return Err(syntax_data.positions),
+  };
+  let ast_root = match AstRoot::cast(SyntaxNode::new_root(reparsed.green)) {
+    Some(x) => x,
+    None => return Err(syntax_data.positions),
+  };
+  let old = reparsed.old;
+  let new_len = reparsed.new_len;
+  let is_inner =
+    |r: TextRange| old.start() < r.start() && r.start() < old.end();
+  let mut uses = syntax_data.uses;
+  for u in uses.iter_mut() {
+    u.range = shift(u.range, old, new_len);
+  }
+  let mut lex_errors = syntax_data.errors.lex;
+  lex_errors.retain(|e| !is_inner(e.range));
+  for e in lex_errors.iter_mut() {
+    e.range = shift(e.range, old, new_len);
+  }
+  lex_errors.extend(reparsed.lex_errors.into_iter().map(|mut e| {
+    e.range += old.start();
+    e
+  }));
+  let mut parse_errors = syntax_data.errors.parse;
+  parse_errors.retain(|e| !is_inner(e.range));
+  for e in parse_errors.iter_mut() {
+    e.range = shift(e.range, old, new_len);
+  }
+  parse_errors.extend(reparsed.parse_errors.into_iter().map(|mut e| {
+    e.range += old.start();
+    e
+  }));
+  lex_errors.sort_by_key(|e| e.range.start());
+  parse_errors.sort_by_key(|e| e.range.start());
+  Ok(SyntaxData::new(
+    syntax_data.positions,
+    ast_root,
+    uses,
+    lex_errors,
+    parse_errors,
+  ))
+}
+
+/// The result of reparsing part of a file.
+struct Reparsed {
+  /// the new green tree for the whole file.
+  green: GreenNode,
+  /// the old range of the part that was reparsed.
+  old: TextRange,
+  /// the new length of the part that was reparsed.
+  new_len: TextSize,
+  /// the errors from lexing the part, relative to its start.
+  lex_errors: Vec<lex::Error>,
+  /// the errors from parsing the part, relative to its start.
+  parse_errors: Vec<parse::Error>,
+}
+
+/// Reparses the token containing the edit, if the edit doesn't change the kind
+/// of that token or the tokens around it.
+fn token(
+  syntax_data: &SyntaxData,
+  range: TextRange,
+  text: &str,
+) -> Option<Reparsed> {
+  let root = syntax_data.ast_root.syntax();
+  let tok = match root.covering_element(range) {
+    NodeOrToken::Token(tok) => tok,
+    NodeOrToken::Node(_) => return None,
+  };
+  let old = tok.text_range();
+  if tok.kind() == SK::Pragma
+    || syntax_data
+      .errors
+      .lex
+      .iter()
+      .any(|e| e.range.intersect(old).is_some())
+  {
+    return None;
+  }
+  let mut new_text = tok.text().to_owned();
+  new_text
+    .replace_range(std::ops::Range::<usize>::from(range - old.start()), text);
+  // the tokens just before and after may also change, e.g. if we add a `/`
+  // after a `/`.
+  let prev = tok.prev_token();
+  let next = tok.next_token();
+  let mut full = String::new();
+  let mut want = Vec::with_capacity(3);
+  if let Some(prev) = prev.as_ref() {
+    full.push_str(prev.text());
+    want.push((prev.kind(), prev.text()));
+  }
+  full.push_str(&new_text);
+  want.push((tok.kind(), new_text.as_str()));
+  if let Some(next) = next.as_ref() {
+    full.push_str(next.text());
+    want.push((next.kind(), next.text()));
+  }
+  let lexed = lex::get(&full);
+  let same = lexed.errors.is_empty()
+    && lexed.uses.is_empty()
+    && lexed.tokens.len() == want.len()
+    && lexed
+      .tokens
+      .iter()
+      .zip(want.iter())
+      .all(|(got, &(kind, text))| got.kind == kind && got.text == text);
+  if !same {
+    return None;
+  }
+  Some(Reparsed {
+    green: tok.replace_with(GreenToken::new(tok.kind().into(), &new_text)),
+    old,
+    new_len: TextSize::of(new_text.as_str()),
+    lex_errors: Vec::new(),
+    parse_errors: Vec::new(),
+  })
+}
+
+/// Reparses the smallest block containing the edit, if the edit doesn't touch
+/// the braces of that block, and the block is still a single block after the
+/// edit.
+fn block(
+  syntax_data: &SyntaxData,
+  range: TextRange,
+  text: &str,
+) -> Option<Reparsed> {
+  let root = syntax_data.ast_root.syntax();
+  let node = match root.covering_element(range) {
+    NodeOrToken::Node(node) => node,
+    NodeOrToken::Token(tok) => tok.parent()?,
+  };
+  let node = node.ancestors().find(|node| {
+    let r = node.text_range();
+    node.kind() == SK::BlockStmt
+      && r.start() < range.start()
+      && range.end() < r.end()
+  })?;
+  let old = node.text_range();
+  // the pragmas are lexed specially and resolved later, so give up if the edit
+  // could add or remove any.
+  let has_pragma = node
+    .descendants_with_tokens()
+    .any(|x| x.kind() == SK::Pragma);
+  if has_pragma {
+    return None;
+  }
+  let mut new_text = node.text().to_string();
+  new_text
+    .replace_range(std::ops::Range::<usize>::from(range - old.start()), text);
+  let lexed = lex::get(&new_text);
+  // the braces must match up such that the first one is closed by the last
+  // one, else the block would swallow (or be cut short by) the tokens after it.
+  let mut depth = 0usize;
+  for (idx, tok) in lexed.tokens.iter().enumerate() {
+    match tok.kind {
+      SK::Pragma => return None,
+      SK::LCurly => depth += 1,
+      SK::RCurly => {
+        depth = depth.checked_sub(1)?;
+        if depth == 0 && idx + 1 != lexed.tokens.len() {
+          return None;
+        }
+      }
+      _ => {}
+    }
+  }
+  if depth != 0 {
+    return None;
+  }
+  let parsed = parse::get_block(&lexed.tokens)?;
+  Some(Reparsed {
+    green: node.replace_with(parsed.block.syntax().green().into_owned()),
+    old,
+    new_len: TextSize::of(new_text.as_str()),
+    lex_errors: lexed.errors,
+    parse_errors: parsed.errors,
+  })
+}
+
+/// Adjusts a range that is not inside `old` for `old` being replaced by text
+/// of length `new_len`.
+fn shift(range: TextRange, old: TextRange, new_len: TextSize) -> TextRange {
+  let f = |x: TextSize| {
+    if x >= old.end() {
+      x - old.len() + new_len
+    } else {
+      x
+    }
+  };
+  TextRange::new(f(range.start()), f(range.end()))
+}
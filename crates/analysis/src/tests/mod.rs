@@ -1,7 +1,8 @@
 mod other;
 mod support;
 
-use support::{check, check_many};
+use crate::{Config, Level, Lints};
+use support::{check, check_many, check_with};
 
 #[test]
 fn transitive() {
@@ -58,11 +59,37 @@ fn hover_ambiguous() {
   check(include_str!("data/hover_ambiguous.c0"))
 }
 
+#[test]
+fn hover_decl() {
+  check(include_str!("data/hover_decl.c0"))
+}
+
 #[test]
 fn hover_ty() {
   check(include_str!("data/hover_ty.c0"))
 }
 
+#[test]
+fn level_c0() {
+  let config = Config {
+    level: Level::C0,
+    ..Config::default()
+  };
+  check_with(config, &[("/main.c0", include_str!("data/level_c0.c0"))])
+}
+
+#[test]
+fn lints() {
+  let config = Config {
+    lints: Lints {
+      suspicious: true,
+      style: true,
+    },
+    ..Config::default()
+  };
+  check_with(config, &[("/main.c0", include_str!("data/lints.c0"))])
+}
+
 #[test]
 fn lv_bad() {
   check(include_str!("data/lv_bad.c0"))
@@ -93,6 +120,11 @@ fn return_bad() {
   check(include_str!("data/return_bad.c0"))
 }
 
+#[test]
+fn semantic_tokens() {
+  check(include_str!("data/semantic_tokens.c0"))
+}
+
 #[test]
 fn ty_bad() {
   check(include_str!("data/ty_bad.c0"))
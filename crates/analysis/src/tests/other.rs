@@ -1,7 +1,17 @@
 use super::support::uri;
 use crate::db::Db;
-use crate::types::Update;
+use crate::position_db::PositionDb;
+use crate::types::{
+  CompletionKind, Config, Edit, FoldingRangeKind, Lints, MemoryUsage, Severity,
+  Target, Update, Verbosity,
+};
+use crate::{Position, Range};
 use rustc_hash::FxHashMap;
+use std::rc::Rc;
+use std_lib::Lib;
+use syntax::ast::AstNode as _;
+use syntax::rowan::{TextRange, TextSize};
+use uri_db::Uri;
 
 #[test]
 fn change() {
@@ -32,3 +42,1011 @@ fn change() {
   assert_eq!(c_ds[0].message, "no such path");
   assert_eq!(c_ds[1].message, "undefined function `foo`");
 }
+
+#[test]
+fn overlay() {
+  let good = "int main() { return 0; }";
+  let bad = "int main() { return true; }";
+  let messages = |db: &Db| {
+    let ds: FxHashMap<_, _> = db.all_diagnostics().into_iter().collect();
+    ds.get(&uri("/a.c0")).map(|ds| ds.len())
+  };
+  let mut db = Db::new(vec![(uri("/a.c0"), good.to_owned())]);
+  assert_eq!(messages(&db), Some(0));
+  // the buffer takes precedence over the disk.
+  db.open_file(uri("/a.c0"), bad.to_owned());
+  assert_eq!(messages(&db), Some(1));
+  db.update_files(vec![Update::Create(uri("/a.c0"), good.to_owned())]);
+  assert_eq!(messages(&db), Some(1));
+  // but we go back to the disk on close.
+  db.close_file(&uri("/a.c0"));
+  assert_eq!(messages(&db), Some(0));
+  // saving means the buffer is on disk.
+  db.open_file(uri("/a.c0"), bad.to_owned());
+  db.save_file(&uri("/a.c0"));
+  db.close_file(&uri("/a.c0"));
+  assert_eq!(messages(&db), Some(1));
+  // files not on disk go away on close.
+  db.update_files(vec![Update::Delete(uri("/a.c0"))]);
+  assert_eq!(messages(&db), None);
+  db.open_file(uri("/a.c0"), bad.to_owned());
+  assert_eq!(messages(&db), Some(1));
+  db.update_files(vec![Update::Delete(uri("/a.c0"))]);
+  assert_eq!(messages(&db), Some(1));
+  db.close_file(&uri("/a.c0"));
+  assert_eq!(messages(&db), None);
+}
+
+#[test]
+fn edit_transitive() {
+  let h = r#"
+    int foo();
+  "#;
+  let m = r#"
+    #use "h.h0"
+    int bar();
+  "#;
+  let source = r#"
+    #use "m.h0"
+
+    int main() {
+      return foo() + bar();
+    }
+  "#;
+  let mut db = Db::new(vec![
+    (uri("/h.h0"), h.to_owned()),
+    (uri("/m.h0"), m.to_owned()),
+    (uri("/c.c0"), source.to_owned()),
+  ]);
+  let set = |db: &mut Db, name: &str, text: &str| {
+    let edit = Edit {
+      text: text.to_owned(),
+      range: None,
+    };
+    db.edit_file(&uri(name), vec![edit]);
+  };
+  let messages = |db: &Db| {
+    let mut ret: Vec<_> = db
+      .all_diagnostics()
+      .into_iter()
+      .flat_map(|(_, ds)| ds.into_iter().map(|d| d.message))
+      .collect();
+    ret.sort_unstable();
+    ret
+  };
+  assert!(messages(&db).is_empty());
+  // changes the exports of a file used only transitively.
+  set(&mut db, "/h.h0", "int quz();");
+  assert_eq!(messages(&db), ["undefined function `foo`"]);
+  // doesn't change the exports.
+  set(&mut db, "/h.h0", "// a comment\nint quz();");
+  assert_eq!(messages(&db), ["undefined function `foo`"]);
+  set(&mut db, "/h.h0", h);
+  assert!(messages(&db).is_empty());
+  // changes the file dependencies.
+  set(&mut db, "/m.h0", "int bar();");
+  assert_eq!(messages(&db), ["undefined function `foo`"]);
+  set(&mut db, "/m.h0", m);
+  assert!(messages(&db).is_empty());
+}
+
+#[test]
+fn lazy() {
+  let a = r#"
+    #use <conio>
+    int main() {
+      return 1;
+    }
+  "#;
+  let b = r#"
+    int foo() {
+      return 2;
+    }
+  "#;
+  let db = Db::new(vec![
+    (uri("/a.c0"), a.to_owned()),
+    (uri("/b.c0"), b.to_owned()),
+  ]);
+  let a_id = db.uris.get_id(&uri("/a.c0")).unwrap();
+  let b_id = db.uris.get_id(&uri("/b.c0")).unwrap();
+  let pos = Position {
+    line: 3,
+    character: 13,
+  };
+  assert!(db.hover(&uri("/a.c0"), pos).is_some());
+  assert!(db.files[&a_id].memos.semantic.borrow().is_some());
+  assert!(db.files[&b_id].syntax.borrow().is_none());
+  assert!(db.files[&b_id].memos.semantic.borrow().is_none());
+  assert!(db.std_lib.get_computed(Lib::Conio).is_some());
+  assert!(db.std_lib.get_computed(Lib::String).is_none());
+}
+
+#[test]
+fn early_cutoff() {
+  let h = r#"
+    int foo();
+  "#;
+  let m = r#"
+    #use "h.h0"
+    int bar();
+  "#;
+  let mut db = Db::new(vec![
+    (uri("/h.h0"), h.to_owned()),
+    (uri("/m.h0"), m.to_owned()),
+  ]);
+  let m_id = db.uris.get_id(&uri("/m.h0")).unwrap();
+  let set = |db: &mut Db, text: &str| {
+    let old = db.semantic_data(m_id).unwrap();
+    let edit = Edit {
+      text: text.to_owned(),
+      range: None,
+    };
+    db.edit_file(&uri("/h.h0"), vec![edit]);
+    !Rc::ptr_eq(&old, &db.semantic_data(m_id).unwrap())
+  };
+  // same exports, so no need to re-check m.
+  assert!(!set(&mut db, "// not a doc comment\n\nint foo();"));
+  // different exports.
+  assert!(set(&mut db, "int foo(int x);"));
+}
+
+#[test]
+fn edit_earlier_body() {
+  let before = r#"
+int main() {
+  return 0;
+}
+
+int foo(int y) {
+  int x = true;
+  return x + y;
+}
+"#;
+  let after = r#"
+int main() {
+  int z = 1 + 2;
+  return z;
+}
+
+int foo(int y) {
+  int x = true;
+  return x + y;
+}
+"#;
+  let mut db = Db::new(vec![(uri("/a.c0"), before.to_owned())]);
+  assert_eq!(db.all_diagnostics()[0].1.len(), 1);
+  let edit = Edit {
+    text: after.to_owned(),
+    range: None,
+  };
+  db.edit_file(&uri("/a.c0"), vec![edit]);
+  let fresh = Db::new(vec![(uri("/a.c0"), after.to_owned())]);
+  assert_eq!(db.all_diagnostics(), fresh.all_diagnostics());
+  for line in 0..after.lines().count() {
+    for character in 0..20 {
+      let pos = Position {
+        line: line as u32,
+        character,
+      };
+      let got = db.hover(&uri("/a.c0"), pos).map(|x| (x.range, x.contents));
+      let want = fresh
+        .hover(&uri("/a.c0"), pos)
+        .map(|x| (x.range, x.contents));
+      assert_eq!(got, want);
+    }
+  }
+}
+
+#[test]
+fn reparse() {
+  let mut text = "int main() {\n  int x = 1;\n  return x;\n}\n".to_owned();
+  let mut db = Db::new(vec![(uri("/a.c0"), text.clone())]);
+  let id = db.uris.get_id(&uri("/a.c0")).unwrap();
+  db.syntax_data(id);
+  // each step replaces `old`, found just after `before`, with `new`.
+  let steps = [
+    // in a token.
+    ("int x = ", "1", "123", true),
+    ("  int ", "x", "xyz", true),
+    // in a block, with a new line.
+    ("xyz = 123;\n", "", "  int y = 2;\n", true),
+    // in a block, with a parse error, and then without it.
+    ("return x", "", "+", true),
+    ("return x", "+", "", true),
+    ("return x;", "", " // é", true),
+    ("// ", "", "hi ", true),
+    // unbalanced braces.
+    ("main() {\n", "", "{\n", false),
+  ];
+  for (before, old, new, incremental) in steps {
+    let start = text.find(before).unwrap() + before.len();
+    let end = start + old.len();
+    assert_eq!(&text[start..end], old);
+    let range = TextRange::new(
+      TextSize::try_from(start).unwrap(),
+      TextSize::try_from(end).unwrap(),
+    );
+    let edit = Edit {
+      text: new.to_owned(),
+      range: Some(PositionDb::new(&text).range(range).unwrap()),
+    };
+    text.replace_range(start..end, new);
+    db.edit_file(&uri("/a.c0"), vec![edit]);
+    assert_eq!(
+      db.files[&id].syntax.borrow().is_some(),
+      incremental,
+      "{text}"
+    );
+    let fresh = Db::new(vec![(uri("/a.c0"), text.clone())]);
+    let got = db.syntax_data(id);
+    let want = fresh.syntax_data(fresh.uris.get_id(&uri("/a.c0")).unwrap());
+    assert_eq!(
+      format!("{:#?}", got.ast_root.syntax()),
+      format!("{:#?}", want.ast_root.syntax())
+    );
+    for idx in 0..=text.len() {
+      let r = TextRange::empty(TextSize::try_from(idx).unwrap());
+      assert_eq!(got.positions.range(r), want.positions.range(r));
+    }
+    assert_eq!(db.all_diagnostics(), fresh.all_diagnostics());
+  }
+}
+
+#[test]
+fn positions() {
+  let mut text = "// é\nint f() {\n  return 1; // 😀 x\n}\n".to_owned();
+  let mut ps = PositionDb::new(&text);
+  let check = |ps: &PositionDb, text: &str| {
+    for idx in 0..=text.len() {
+      let r = TextRange::empty(TextSize::try_from(idx).unwrap());
+      let got = ps.range(r);
+      if !text.is_char_boundary(idx) {
+        assert_eq!(got, None);
+        continue;
+      }
+      let before = &text[..idx];
+      let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+      let want = Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+      };
+      assert_eq!(got.unwrap().start, want);
+      assert_eq!(ps.text_range(got.unwrap()), Some(r));
+    }
+  };
+  check(&ps, &text);
+  let backwards = Range {
+    start: Position {
+      line: 1,
+      character: 0,
+    },
+    end: Position {
+      line: 0,
+      character: 1,
+    },
+  };
+  assert_eq!(ps.text_range(backwards), None);
+  for (old, new) in [("é", "ab"), ("1", "ñ\n"), ("😀 x\n", ""), ("\n", "")]
+  {
+    let start = text.find(old).unwrap();
+    let end = start + old.len();
+    let range = TextRange::new(
+      TextSize::try_from(start).unwrap(),
+      TextSize::try_from(end).unwrap(),
+    );
+    text.replace_range(start..end, new);
+    ps.edit(range, new);
+    check(&ps, &text);
+  }
+}
+
+#[test]
+fn junk_items() {
+  // this used to loop forever, trying to parse an item from the `=`.
+  let text = "int x = 3;\n}\n";
+  let db = Db::new(vec![(uri("/a.c0"), text.to_owned())]);
+  let diagnostics = db.all_diagnostics();
+  assert!(diagnostics.iter().any(|(_, ds)| !ds.is_empty()));
+}
+
+#[test]
+fn panic_mid_check() {
+  let a =
+    "int f() { return 1; }\nint g() { return 2; }\nint h() { return 3; }\n";
+  let b = "int main() { return 0; }\n";
+  let db = Db::new(vec![
+    (uri("/a.c0"), a.to_owned()),
+    (uri("/b.c0"), b.to_owned()),
+  ]);
+  let a_id = db.uris.get_id(&uri("/a.c0")).unwrap();
+  let (item, _) = db
+    .syntax_data(a_id)
+    .hir_root
+    .arenas
+    .item
+    .iter()
+    .last()
+    .unwrap();
+  // like a check of `a.c0` that reported an error and then panicked.
+  let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+    db.cx.borrow_mut().errors.push(statics::Error {
+      id: statics::Id::Item(item),
+      kind: statics::ErrorKind::CannotAssign,
+    });
+    panic!("mid-check");
+  }));
+  assert!(res.is_err());
+  // `b.c0` has no such item, and no errors.
+  let (diagnostics, _) = db.file_diagnostics(&uri("/b.c0")).unwrap();
+  assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+}
+
+#[test]
+fn memory_usage() {
+  let text = "int f() { return 1; }";
+  let mut db = Db::new(vec![(uri("/a.c0"), text.to_owned())]);
+  let before = db.memory_usage();
+  assert_eq!(before.files, 1);
+  assert_eq!(before.total(), before.contents);
+  db.all_diagnostics();
+  let after = db.memory_usage();
+  assert!(after.syntax_trees > 0);
+  assert!(after.hir > 0);
+  assert!(after.ptrs > 0);
+  assert!(after.envs > 0);
+  db.update_files(vec![Update::Delete(uri("/a.c0"))]);
+  assert_eq!(db.memory_usage(), MemoryUsage::default());
+}
+
+#[test]
+fn diagnostics_hash() {
+  let text = "int f() { return true; }";
+  let mut db = Db::new(vec![(uri("/a.c0"), text.to_owned())]);
+  let (ds, hash) = db.file_diagnostics(&uri("/a.c0")).unwrap();
+  assert_eq!(ds.len(), 1);
+  let mut set = |text: &str| {
+    let edit = Edit {
+      text: text.to_owned(),
+      range: None,
+    };
+    db.edit_file(&uri("/a.c0"), vec![edit]);
+    db.file_diagnostics(&uri("/a.c0")).unwrap().1
+  };
+  assert_eq!(set("int f() { return true; } // hi"), hash);
+  assert_ne!(set("int f() { return 3; }"), hash);
+  assert_eq!(set(text), hash);
+}
+
+#[test]
+fn use_cycle() {
+  let mut db = Db::new(vec![
+    (uri("/a.h0"), "#use \"b.h0\"\n".to_owned()),
+    (uri("/b.h0"), "int f();\n".to_owned()),
+  ]);
+  assert_eq!(db.use_cycle(), None);
+  let edit = Edit {
+    text: "#use \"a.h0\"\n".to_owned(),
+    range: None,
+  };
+  db.edit_file(&uri("/b.h0"), vec![edit]);
+  let cycle = db.use_cycle().unwrap();
+  assert!(cycle == uri("/a.h0") || cycle == uri("/b.h0"));
+  assert_eq!(db.diagnostics_by_file().len(), 2);
+}
+
+#[test]
+fn inspect() {
+  let text = "int f(int x) { return x + 1; }\n";
+  let db = Db::new(vec![(uri("/a.c0"), text.to_owned())]);
+  let a = uri("/a.c0");
+  let tree = db.syntax_tree(&a).unwrap();
+  assert!(tree.starts_with("Root@0..31\n  FnItem@0..30\n"));
+  let tokens = db.tokens(&a).unwrap();
+  assert!(tokens.starts_with("IntKw@0:0..0:3 \"int\"\n"));
+  assert!(tokens.ends_with("Whitespace@0:30..1:0 \"\\n\"\n"));
+  let hir = r#"# items
+item0 = ty1 f(ty0 x) stmt1
+
+# statements
+stmt0 = return expr2
+stmt1 = { stmt0 }
+
+# simple statements
+
+# expressions
+expr0: int = x
+expr1: int = <int>
+expr2: int = expr0 + expr1
+
+# types
+ty0: int = int
+ty1: int = int
+"#;
+  assert_eq!(db.hir(&a).unwrap(), hir);
+  assert!(db.hir(&uri("/b.c0")).is_none());
+}
+
+#[test]
+fn folding_ranges() {
+  let source = r#"#use <conio>
+#use <string>
+
+/* a long
+   comment */
+struct s {
+  int x;
+};
+
+int main() {
+  if (true) {
+    return 1;
+  }
+  return 0;
+}
+"#;
+  let db = Db::new(vec![(uri("/c.c0"), source.to_owned())]);
+  let mut got: Vec<_> = db
+    .folding_ranges(&uri("/c.c0"))
+    .unwrap()
+    .into_iter()
+    .map(|x| (x.kind, x.range.start.line, x.range.end.line))
+    .collect();
+  got.sort_unstable();
+  assert_eq!(
+    got,
+    vec![
+      (FoldingRangeKind::Region, 5, 7),
+      (FoldingRangeKind::Region, 9, 14),
+      (FoldingRangeKind::Region, 10, 12),
+      (FoldingRangeKind::Comment, 3, 4),
+      (FoldingRangeKind::Imports, 0, 1),
+    ]
+  );
+}
+
+#[test]
+fn selection_ranges() {
+  let source = r#"int main() {
+  return 1 + 2;
+}
+"#;
+  let db = Db::new(vec![(uri("/c.c0"), source.to_owned())]);
+  let pos = Position {
+    line: 1,
+    character: 9,
+  };
+  let got = db.selection_ranges(&uri("/c.c0"), &[pos]).unwrap();
+  assert_eq!(got.len(), 1);
+  let got: Vec<_> = got[0]
+    .iter()
+    .map(|r| (r.start.line, r.start.character, r.end.line, r.end.character))
+    .collect();
+  assert_eq!(
+    got,
+    vec![
+      // `1`, `1 + 2`, `return 1 + 2;`, the body, the fn, the root
+      (1, 9, 1, 10),
+      (1, 9, 1, 14),
+      (1, 2, 1, 15),
+      (0, 11, 2, 1),
+      (0, 0, 2, 1),
+      (0, 0, 3, 0),
+    ]
+  );
+  // a position we can't resolve still gets a result.
+  let past_end = Position {
+    line: 9,
+    character: 0,
+  };
+  let got = db
+    .selection_ranges(&uri("/c.c0"), &[past_end, pos])
+    .unwrap();
+  assert_eq!(got.len(), 2);
+  assert_eq!(
+    got[0],
+    vec![Range {
+      start: past_end,
+      end: past_end
+    }]
+  );
+  assert_eq!(got[1].len(), 6);
+}
+
+#[test]
+fn call_hierarchy() {
+  let header = r#"int foo(int x);
+"#;
+  let source = r#"#use "h.h0"
+#use <conio>
+
+int foo(int x) {
+  return x;
+}
+
+int bar() {
+  printint(1);
+  return foo(1) + foo(2);
+}
+
+int main() {
+  return bar() + foo(3);
+}
+"#;
+  let db = Db::new(vec![
+    (uri("/h.h0"), header.to_owned()),
+    (uri("/c.c0"), source.to_owned()),
+  ]);
+  assert_eq!(db.calls().len(), 5);
+  let pos = Position {
+    line: 3,
+    character: 4,
+  };
+  let foo = db.fn_item(&uri("/c.c0"), pos).unwrap();
+  assert_eq!(foo.name, "foo");
+  assert_eq!(foo.signature, "int foo(int x)");
+  let pos = Position {
+    line: 13,
+    character: 9,
+  };
+  let bar = db.fn_item(&uri("/c.c0"), pos).unwrap();
+  assert_eq!(bar.name, "bar");
+  assert_eq!(bar.range.start.line, 7);
+  let incoming: Vec<_> = db
+    .incoming_calls(&foo)
+    .into_iter()
+    .map(|(item, ranges)| (item.name, ranges.len()))
+    .collect();
+  assert_eq!(
+    incoming,
+    vec![("bar".to_owned(), 2), ("main".to_owned(), 1)]
+  );
+  // `printint` is from the std lib, so it has no location.
+  let outgoing: Vec<_> = db
+    .outgoing_calls(&bar)
+    .into_iter()
+    .map(|(item, ranges)| (item.name, ranges.len()))
+    .collect();
+  assert_eq!(outgoing, vec![("foo".to_owned(), 2)]);
+}
+
+#[test]
+fn call_hierarchy_same_name() {
+  let a = r#"int f() {
+  return 1;
+}
+
+int main() {
+  return f();
+}
+"#;
+  let b = r#"int f() {
+  return 2;
+}
+
+int g() {
+  return f() + f();
+}
+"#;
+  let db = Db::new(vec![
+    (uri("/a.c0"), a.to_owned()),
+    (uri("/b.c0"), b.to_owned()),
+  ]);
+  let pos = Position {
+    line: 0,
+    character: 4,
+  };
+  let incoming = |file| {
+    let f = db.fn_item(&uri(file), pos).unwrap();
+    db.incoming_calls(&f)
+      .into_iter()
+      .map(|(item, ranges)| (item.name, item.uri, ranges.len()))
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(
+    incoming("/a.c0"),
+    vec![("main".to_owned(), uri("/a.c0"), 1)]
+  );
+  assert_eq!(incoming("/b.c0"), vec![("g".to_owned(), uri("/b.c0"), 2)]);
+  let outgoing = |file, line| {
+    let pos = Position { line, character: 4 };
+    let f = db.fn_item(&uri(file), pos).unwrap();
+    db.outgoing_calls(&f)
+      .into_iter()
+      .map(|(item, ranges)| (item.name, item.uri, ranges.len()))
+      .collect::<Vec<_>>()
+  };
+  assert_eq!(
+    outgoing("/a.c0", 4),
+    vec![("f".to_owned(), uri("/a.c0"), 1)]
+  );
+  assert_eq!(
+    outgoing("/b.c0", 4),
+    vec![("f".to_owned(), uri("/b.c0"), 2)]
+  );
+}
+
+#[test]
+fn hover_multi_line() {
+  let source = r#"#use <rand>
+struct s {
+  int x;
+  bool y;
+};
+"#;
+  let db = Db::new(vec![(uri("/c.c0"), source.to_owned())]);
+  let hover = |line, character| {
+    db.hover(&uri("/c.c0"), Position { line, character })
+      .unwrap()
+      .contents
+      .to_string()
+  };
+  assert_eq!(
+    hover(0, 7),
+    r#"```c0
+#use <rand>
+struct rand* init_rand(int seed);
+int rand(struct rand* gen);
+```"#
+  );
+  assert_eq!(
+    hover(1, 7),
+    r#"```c0
+struct s {
+  int x;
+  bool y;
+};
+```"#
+  );
+}
+
+#[test]
+fn doc_comments() {
+  let header = r#"// Returns `x` plus one.
+//
+// Never fails.
+int inc(int x);
+
+/* not a doc, since
+   there's a blank line */
+
+typedef int t; // not a doc for `s`
+/**
+ * A struct.
+ */
+struct s {
+  t x;
+};
+"#;
+  let source = r#"#use "h.h0"
+#use <conio>
+
+int inc(int x) {
+  return x + 1;
+}
+
+int main() {
+  printint(inc(3));
+  return 0;
+}
+"#;
+  let db = Db::new(vec![
+    (uri("/h.h0"), header.to_owned()),
+    (uri("/c.c0"), source.to_owned()),
+  ]);
+  let hover = |name: &str, line, character| {
+    db.hover(&uri(name), Position { line, character })
+      .unwrap()
+      .doc
+  };
+  let inc_doc = "Returns `x` plus one.\n\nNever fails.";
+  assert_eq!(hover("/c.c0", 8, 13).as_deref(), Some(inc_doc));
+  assert_eq!(hover("/c.c0", 3, 5).as_deref(), Some(inc_doc));
+  assert_eq!(
+    hover("/c.c0", 8, 4).as_deref(),
+    Some("Prints `i` to standard output.")
+  );
+  assert_eq!(hover("/h.h0", 8, 12), None);
+  assert_eq!(hover("/h.h0", 12, 8).as_deref(), Some("A struct."));
+  let pos = Position {
+    line: 9,
+    character: 2,
+  };
+  let completions = db.completions(&uri("/c.c0"), pos).unwrap();
+  let inc = completions.iter().find(|x| x.label == "inc").unwrap();
+  assert_eq!(inc.detail.as_deref(), Some("int inc(int x)"));
+  assert_eq!(inc.doc.as_deref(), Some(inc_doc));
+}
+
+#[test]
+fn doc_contracts() {
+  let source = r#"// Returns the square root of `x`.
+int isqrt(int x)
+//@requires x >= 0;
+/*@ensures \result * \result <= x; @*/
+{
+  //@assert x >= 0;
+  return 0;
+}
+
+int abs(int x)
+//@ensures \result >= 0;
+{
+  return x < 0 ? -x : x;
+}
+
+int main() {
+  return isqrt(abs(3));
+}
+"#;
+  let db = Db::new(vec![(uri("/c.c0"), source.to_owned())]);
+  let hover = |line, character| {
+    db.hover(&uri("/c.c0"), Position { line, character })
+      .unwrap()
+      .doc
+  };
+  let isqrt_doc = r#"Returns the square root of `x`.
+
+```c0
+//@requires x >= 0;
+/*@ensures \result * \result <= x; @*/
+```"#;
+  let abs_doc = r#"```c0
+//@ensures \result >= 0;
+```"#;
+  assert_eq!(hover(16, 10).as_deref(), Some(isqrt_doc));
+  assert_eq!(hover(16, 16).as_deref(), Some(abs_doc));
+  let pos = Position {
+    line: 16,
+    character: 9,
+  };
+  let completions = db.completions(&uri("/c.c0"), pos).unwrap();
+  let isqrt = completions.iter().find(|x| x.label == "isqrt").unwrap();
+  assert_eq!(isqrt.doc.as_deref(), Some(isqrt_doc));
+}
+
+#[test]
+fn completions_vars() {
+  let source = r#"int f(int a) {
+  int b = 3;
+  if (true) {
+    bool c = false;
+  }
+  
+}
+"#;
+  let db = Db::new(vec![(uri("/c.c0"), source.to_owned())]);
+  let pos = Position {
+    line: 5,
+    character: 2,
+  };
+  let mut got: Vec<_> = db
+    .completions(&uri("/c.c0"), pos)
+    .unwrap()
+    .into_iter()
+    .filter(|x| matches!(x.kind, CompletionKind::Var))
+    .map(|x| (x.label, x.detail.unwrap()))
+    .collect();
+  got.sort_unstable();
+  assert_eq!(
+    got,
+    vec![
+      ("a".to_owned(), "int a".to_owned()),
+      ("b".to_owned(), "int b".to_owned()),
+    ]
+  );
+}
+
+#[test]
+fn targets() {
+  let lib = r#"
+    int inc(int x) {
+      return x + 1;
+    }
+  "#;
+  let main = r#"
+    int main() {
+      return inc(1);
+    }
+  "#;
+  let mut db = Db::new(vec![
+    (uri("/lib.c0"), lib.to_owned()),
+    (uri("/main.c0"), main.to_owned()),
+  ]);
+  let main_ds = |db: &Db| db.file_diagnostics(&uri("/main.c0")).unwrap().0;
+  assert_eq!(main_ds(&db)[0].message, "undefined function `inc`");
+  db.set_targets(vec![Target {
+    name: "main".to_owned(),
+    files: vec![uri("/lib.c0"), uri("/main.c0")],
+  }]);
+  assert!(main_ds(&db).is_empty());
+  let pos = Position {
+    line: 2,
+    character: 14,
+  };
+  assert!(db.hover(&uri("/main.c0"), pos).is_some());
+  // the order matters.
+  db.set_targets(vec![Target {
+    name: "main".to_owned(),
+    files: vec![uri("/main.c0"), uri("/lib.c0")],
+  }]);
+  assert_eq!(main_ds(&db)[0].message, "undefined function `inc`");
+}
+
+#[test]
+fn targets_use_loaded() {
+  let h = r#"
+    struct s {
+      int x;
+    };
+  "#;
+  let a = r#"
+    #use "h.h0"
+    int get(struct s* p) {
+      return p->x;
+    }
+  "#;
+  let b = r#"
+    #use "h.h0"
+    int main() {
+      struct s* p = alloc(struct s);
+      return get(p);
+    }
+  "#;
+  let mut db = Db::new(vec![
+    (uri("/h.h0"), h.to_owned()),
+    (uri("/a.c0"), a.to_owned()),
+    (uri("/b.c0"), b.to_owned()),
+  ]);
+  db.set_targets(vec![Target {
+    name: "main".to_owned(),
+    files: vec![uri("/a.c0"), uri("/b.c0")],
+  }]);
+  // `h.h0` was already loaded by `a.c0`, so `b.c0` doesn't load it again.
+  assert!(db.all_diagnostics().iter().all(|(_, ds)| ds.is_empty()));
+}
+
+#[test]
+fn targets_many() {
+  let lib = "int one() { return 1; }";
+  let main = "int main() { return one(); }";
+  let mut db = Db::new(vec![
+    (uri("/lib.c0"), lib.to_owned()),
+    (uri("/main.c0"), main.to_owned()),
+  ]);
+  db.set_targets(vec![
+    Target {
+      name: "with-lib".to_owned(),
+      files: vec![uri("/lib.c0"), uri("/main.c0")],
+    },
+    Target {
+      name: "without-lib".to_owned(),
+      files: vec![uri("/main.c0"), uri("/missing.c0")],
+    },
+  ]);
+  let (ds, _) = db.file_diagnostics(&uri("/main.c0")).unwrap();
+  assert_eq!(ds.len(), 1);
+  assert_eq!(ds[0].message, "undefined function `one` (in `without-lib`)");
+  // the missing file is used once it exists.
+  db.update_files(vec![Update::Create(uri("/missing.c0"), lib.to_owned())]);
+  let (ds, _) = db.file_diagnostics(&uri("/main.c0")).unwrap();
+  assert_eq!(ds.len(), 1);
+}
+
+#[test]
+fn config() {
+  let main = r#"
+    bool f(bool b) {
+      b = b;
+      return b == true;
+    }
+  "#;
+  let mut db = Db::new(vec![(uri("/main.c0"), main.to_owned())]);
+  let main_ds = |db: &Db| db.file_diagnostics(&uri("/main.c0")).unwrap().0;
+  // the lints are off by default.
+  assert!(main_ds(&db).is_empty());
+  db.set_config(Config {
+    lints: Lints {
+      suspicious: true,
+      style: true,
+    },
+    ..Config::default()
+  });
+  let ds = main_ds(&db);
+  assert_eq!(ds.len(), 2);
+  assert!(ds.iter().all(|d| d.severity == Severity::Warning));
+  db.set_config(Config {
+    lints: Lints {
+      suspicious: false,
+      style: true,
+    },
+    ..Config::default()
+  });
+  let ds = main_ds(&db);
+  assert_eq!(ds.len(), 1);
+  assert_eq!(ds[0].message, "comparison with a `bool` literal");
+  db.set_config(Config {
+    lints: Lints {
+      suspicious: true,
+      style: true,
+    },
+    verbosity: Verbosity::Quiet,
+    ..Config::default()
+  });
+  assert!(main_ds(&db).is_empty());
+}
+
+#[test]
+fn include_dirs() {
+  let lib = "int one();";
+  let main = r#"
+    #use "lib.h0"
+    int main() {
+      return one();
+    }
+  "#;
+  let mut db = Db::new(vec![
+    (uri("/inc/lib.h0"), lib.to_owned()),
+    (uri("/src/main.c0"), main.to_owned()),
+  ]);
+  let main_ds = |db: &Db| db.file_diagnostics(&uri("/src/main.c0")).unwrap().0;
+  assert_eq!(main_ds(&db)[0].message, "no such path");
+  db.set_config(Config {
+    include_dirs: vec![uri("/inc")],
+    ..Config::default()
+  });
+  assert!(main_ds(&db).is_empty());
+  // a file next to the one using it comes first.
+  db.update_files(vec![Update::Create(
+    uri("/src/lib.h0"),
+    "int two();".to_owned(),
+  )]);
+  assert_eq!(main_ds(&db)[0].message, "undefined function `one`");
+}
+
+#[test]
+fn format() {
+  let main = "int main() {\n  return 0;\n}\n";
+  let mut db = Db::new(vec![(uri("/main.c0"), main.to_owned())]);
+  assert_eq!(db.format(&uri("/main.c0")).unwrap(), main);
+  db.set_config(Config {
+    format: fmt::Style {
+      indent_width: 4,
+      tabs: false,
+    },
+    ..Config::default()
+  });
+  let got = db.format(&uri("/main.c0")).unwrap();
+  assert_eq!(got, "int main() {\n    return 0;\n}\n");
+}
+
+#[test]
+fn uses_uris() {
+  let lib = "int one();";
+  let main =
+    |path: &str| format!("#use \"{path}\"\nint main() {{ return one(); }}\n");
+  let check = |lib_uri: Uri, main_uri: Uri, path: &str| {
+    let db = Db::new(vec![
+      (lib_uri, lib.to_owned()),
+      (main_uri.clone(), main(path)),
+    ]);
+    let (ds, _) = db.file_diagnostics(&main_uri).unwrap();
+    assert!(ds.is_empty(), "{main_uri}: {ds:?}");
+  };
+  // the paths have a space and non-ASCII characters, so are percent-encoded.
+  check(
+    uri("/my lib/ünï.h0"),
+    uri("/my src/main.c0"),
+    "../my lib/ünï.h0",
+  );
+  // files only in the editor resolve relative to their URIs too.
+  check(
+    Uri::parse("untitled:my%20lib/%C3%BCn%C3%AF.h0").unwrap(),
+    Uri::parse("untitled:Untitled-1").unwrap(),
+    "my lib/ünï.h0",
+  );
+  check(
+    Uri::parse("vscode-vfs://github/a/b/my%20lib/%C3%BCn%C3%AF.h0").unwrap(),
+    Uri::parse("vscode-vfs://github/a/b/src/main.c0").unwrap(),
+    "../my lib/ünï.h0",
+  );
+}
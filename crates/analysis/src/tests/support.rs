@@ -1,4 +1,7 @@
-use crate::{CodeBlock, Db, Diagnostic, Hover, Position, Range};
+use crate::{
+  CodeBlock, Config, Db, Diagnostic, Hover, Position, Range, SemanticToken,
+  SemanticTokenKind, Severity,
+};
 use rustc_hash::FxHashMap;
 use uri_db::Uri;
 
@@ -11,15 +14,20 @@ pub(crate) fn check(s: &str) {
 }
 
 pub(crate) fn check_many(items: &[(&str, &str)]) {
+  check_with(Config::default(), items)
+}
+
+pub(crate) fn check_with(config: Config, items: &[(&str, &str)]) {
   let files: FxHashMap<_, _> = items
     .iter()
     .map(|&(name, contents)| (uri(name), contents))
     .collect();
-  let db = Db::new(
+  let mut db = Db::new(
     files
       .iter()
       .map(|(uri, &contents)| (uri.clone(), contents.to_owned())),
   );
+  db.set_config(config);
   let all_diagnostics = db.all_diagnostics();
   let mut want_len: usize = 0;
   let mut got_len: usize = 0;
@@ -64,13 +72,19 @@ fn check_one(
       got.message,
       want.message
     );
+    assert_eq!(
+      want.severity, got.severity,
+      "{}: mismatched severity for '{}'",
+      want.range, got.message,
+    );
   }
   for hover in want.hovers.iter() {
     let got_hover = match db.hover(&uri, hover.range.start) {
       None => panic!("no hover at {}", hover.range.start),
       Some(x) => x,
     };
-    assert_eq!(*hover, got_hover);
+    assert_eq!(hover.range, got_hover.range);
+    assert_eq!(hover.contents, got_hover.contents);
   }
   for no_hover in want.no_hovers.iter() {
     assert!(db.hover(&uri, no_hover.start).is_none());
@@ -92,6 +106,12 @@ fn check_one(
     let want = *want.var_defs.get(&var_use.0).unwrap();
     check_def(db, &uri, want, var_use.1.start);
   }
+  if !want.semantic_tokens.is_empty() {
+    let got = db.semantic_tokens(&uri, None).unwrap();
+    for token in want.semantic_tokens.iter() {
+      assert!(got.contains(token), "no semantic token: {:?}", token);
+    }
+  }
 }
 
 fn check_def(db: &Db, uri: &Uri, want: Range, pos: Position) {
@@ -116,6 +136,7 @@ struct Expectations {
   type_def_uses: Vec<(String, Range)>,
   var_defs: FxHashMap<String, Range>,
   var_uses: Vec<(String, Range)>,
+  semantic_tokens: Vec<SemanticToken>,
 }
 
 /// only supports ascii files, and treats all line comments as expectations.
@@ -208,6 +229,12 @@ fn parse_expected(s: &str) -> Expectations {
       "error" => ret.diagnostics.push(Diagnostic {
         range,
         message: content,
+        severity: Severity::Error,
+      }),
+      "warning" => ret.diagnostics.push(Diagnostic {
+        range,
+        message: content,
+        severity: Severity::Warning,
       }),
       "hover" => {
         if content == "<none>" {
@@ -216,6 +243,7 @@ fn parse_expected(s: &str) -> Expectations {
           ret.hovers.push(Hover {
             range,
             contents: CodeBlock::new(content),
+            doc: None,
           })
         }
       }
@@ -229,6 +257,10 @@ fn parse_expected(s: &str) -> Expectations {
       "type-def-use" => ret.type_def_uses.push((content, range)),
       "var-def" => assert!(ret.var_defs.insert(content, range).is_none()),
       "var-use" => ret.var_uses.push((content, range)),
+      "token" => ret.semantic_tokens.push(SemanticToken {
+        range,
+        kind: token_kind(&content),
+      }),
       bad => panic!("unknown expectation kind: {}", bad),
     }
   }
@@ -241,3 +273,16 @@ fn parse_expected(s: &str) -> Expectations {
   ret.var_uses.sort_unstable();
   ret
 }
+
+fn token_kind(s: &str) -> SemanticTokenKind {
+  match s {
+    "typedef" => SemanticTokenKind::TypeDef,
+    "struct" => SemanticTokenKind::Struct,
+    "fn" => SemanticTokenKind::Fn,
+    "param" => SemanticTokenKind::Param,
+    "local" => SemanticTokenKind::Local,
+    "field" => SemanticTokenKind::Field,
+    "lib-fn" => SemanticTokenKind::LibFn,
+    bad => panic!("unknown token kind: {}", bad),
+  }
+}
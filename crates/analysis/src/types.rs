@@ -2,7 +2,7 @@ use std::fmt;
 use text_pos::Range;
 use uri_db::Uri;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Location {
   pub uri: Uri,
   pub range: Range,
@@ -12,6 +12,13 @@ pub struct Location {
 pub struct Diagnostic {
   pub range: Range,
   pub message: String,
+  pub severity: Severity,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+  Error,
+  Warning,
 }
 
 impl fmt::Display for Diagnostic {
@@ -38,6 +45,7 @@ impl fmt::Display for CodeBlock {
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Hover {
   pub contents: CodeBlock,
+  pub doc: Option<String>,
   pub range: Range,
 }
 
@@ -52,3 +60,166 @@ pub enum Update {
   Create(Uri, String),
   Delete(Uri),
 }
+
+/// A named list of files that are compiled together, like the files given to
+/// `cc0`. Each file sees the items of the files before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+  pub name: String,
+  pub files: Vec<Uri>,
+}
+
+/// How to analyze and format files.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Config {
+  pub level: Level,
+  /// Directories to look in for a `#use "..."` that isn't relative to the
+  /// file, in order.
+  pub include_dirs: Vec<Uri>,
+  pub lints: Lints,
+  pub format: ::fmt::Style,
+  pub verbosity: Verbosity,
+}
+
+/// The language level, like the one `cc0` infers from the file extension.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+  /// No `break` or `continue`.
+  C0,
+  /// Everything we support.
+  #[default]
+  C1,
+}
+
+/// Which groups of warnings are enabled. None are by default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Lints {
+  /// Code that is probably a mistake, like assigning a variable to itself.
+  pub suspicious: bool,
+  /// Code that could be simpler, like comparing with `true`.
+  pub style: bool,
+}
+
+/// Which diagnostics to report.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+  /// Only errors.
+  Quiet,
+  /// Errors and warnings.
+  #[default]
+  Normal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SemanticToken {
+  pub range: Range,
+  pub kind: SemanticTokenKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SemanticTokenKind {
+  TypeDef,
+  Struct,
+  Fn,
+  Param,
+  Local,
+  Field,
+  /// A function from a standard library.
+  LibFn,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FoldingRange {
+  pub range: Range,
+  pub kind: FoldingRangeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FoldingRangeKind {
+  Region,
+  Comment,
+  Imports,
+}
+
+/// A function, as part of a call graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FnItem {
+  pub name: String,
+  pub signature: String,
+  pub uri: Uri,
+  /// The range of the whole function.
+  pub range: Range,
+  /// The range of just the name of the function.
+  pub name_range: Range,
+}
+
+/// A call from one function to another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+  pub caller: FnItem,
+  pub callee: String,
+  /// The location of the name of the callee at the call site.
+  pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+  pub label: String,
+  pub kind: CompletionKind,
+  pub detail: Option<String>,
+  pub doc: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+  Var,
+  Fn,
+  TypeDef,
+  Struct,
+}
+
+/// An estimate of how much memory a `Db` uses on the heap, in bytes, for the
+/// files and the memoized data about them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryUsage {
+  pub files: usize,
+  pub contents: usize,
+  pub positions: usize,
+  pub syntax_trees: usize,
+  pub hir: usize,
+  pub ptrs: usize,
+  pub envs: usize,
+  pub diagnostics: usize,
+}
+
+impl MemoryUsage {
+  pub fn total(&self) -> usize {
+    self.contents
+      + self.positions
+      + self.syntax_trees
+      + self.hir
+      + self.ptrs
+      + self.envs
+      + self.diagnostics
+  }
+}
+
+impl fmt::Display for MemoryUsage {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let rows = [
+      ("contents", self.contents),
+      ("positions", self.positions),
+      ("syntax trees", self.syntax_trees),
+      ("hir", self.hir),
+      ("ptrs", self.ptrs),
+      ("envs", self.envs),
+      ("diagnostics", self.diagnostics),
+      ("total", self.total()),
+    ];
+    writeln!(f, "{} files", self.files)?;
+    for (name, bytes) in rows {
+      writeln!(f, "{name:<12} {:>10.1} KiB", bytes as f64 / 1024.0)?;
+    }
+    Ok(())
+  }
+}
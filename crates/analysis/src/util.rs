@@ -1,7 +1,8 @@
 use crate::db::SyntaxData;
-use syntax::ast::AstNode;
+use statics::{Ambiguous, Env};
+use syntax::ast::{AstNode, Param, Simp, SimpOpt, SimpStmt};
 use syntax::rowan::TokenAtOffset;
-use syntax::{SyntaxKind, SyntaxToken};
+use syntax::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
 use text_pos::Position;
 
 pub(crate) fn get_token(
@@ -44,3 +45,50 @@ fn priority(kind: SyntaxKind) -> u8 {
     _ => 1,
   }
 }
+
+/// Returns the node that declares the variable `name`, searching up and to the
+/// left starting at `node`.
+pub(crate) fn get_var_def(
+  syntax_data: &SyntaxData,
+  env: &Env,
+  node: SyntaxNode,
+  name: &str,
+) -> Option<SyntaxNode> {
+  var_decls(syntax_data, env, node.into())
+    .find_map(|(node, tok)| (tok.text() == name).then_some(node))
+}
+
+/// Returns the variable declarations visible from `elem`, innermost first, as
+/// pairs of the declaring node and the declared name.
+pub(crate) fn var_decls<'a>(
+  syntax_data: &'a SyntaxData,
+  env: &'a Env,
+  elem: SyntaxElement,
+) -> impl Iterator<Item = (SyntaxNode, SyntaxToken)> + 'a {
+  // go up and to the left. not quite correct in the case of a decl in the step
+  // of a for loop, but that's an error anyway.
+  std::iter::successors(Some(elem), |elem| {
+    elem
+      .prev_sibling_or_token()
+      .or_else(|| elem.parent().map(Into::into))
+  })
+  .filter_map(|elem| elem.into_node())
+  .filter_map(move |node| {
+    let simp_def = |simp: Simp| match simp {
+      Simp::DeclSimp(simp) => simp.ident(),
+      Simp::AmbiguousSimp(ref amb) => {
+        let simp = syntax_data.ptrs.simp.get(&simp)?;
+        match env.ambiguous_simps.get(simp)? {
+          Ambiguous::Decl(_) => amb.rhs(),
+          Ambiguous::Mul => None,
+        }
+      }
+      _ => None,
+    };
+    let tok = SimpStmt::cast(node.clone())
+      .and_then(|x| simp_def(x.simp()?))
+      .or_else(|| SimpOpt::cast(node.clone()).and_then(|x| simp_def(x.simp()?)))
+      .or_else(|| Param::cast(node.clone()).and_then(|x| x.ident()))?;
+    Some((node, tok))
+  })
+}
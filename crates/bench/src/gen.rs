@@ -0,0 +1,108 @@
+//! Generating synthetic C0 workspaces.
+
+use std::fmt::Write as _;
+
+/// A generated workspace, as paths and contents.
+#[derive(Debug)]
+pub(crate) struct Workspace {
+  pub(crate) files: Vec<(String, String)>,
+}
+
+impl Workspace {
+  pub(crate) fn get(&self, path: &str) -> &str {
+    let (_, contents) = self
+      .files
+      .iter()
+      .find(|(p, _)| p == path)
+      .expect("no such file");
+    contents
+  }
+}
+
+/// Returns a workspace with:
+///
+/// - a chain of `depth` headers, each of which `#use`s the one before it, and
+///   declares `fns` fns, a struct, and a typedef.
+/// - a source file for each header, which `#use`s it, and defines `fns` fns
+///   that call fns declared in it and in the header before it.
+/// - a `main.c0`, which uses the last source file.
+/// - a `big.c0`, which has a single fn of about `big_lines` lines.
+pub(crate) fn workspace(
+  depth: usize,
+  fns: usize,
+  big_lines: usize,
+) -> Workspace {
+  let mut files = Vec::with_capacity(2 * depth + 2);
+  for i in 0..depth {
+    files.push((format!("/h{i}.h0"), header(i, fns)));
+    files.push((format!("/c{i}.c0"), source(i, fns)));
+  }
+  let main = format!(
+    "#use \"c{last}.c0\"\n\nint main() {{\n  return g{last}_0(1, NULL);\n}}\n",
+    last = depth - 1,
+  );
+  files.push(("/main.c0".to_owned(), main));
+  files.push(("/big.c0".to_owned(), big(big_lines)));
+  Workspace { files }
+}
+
+fn header(i: usize, fns: usize) -> String {
+  let mut ret = String::new();
+  // not a doc comment, so editing it doesn't change the exports.
+  ret.push_str("// rev 0\n\n");
+  if i != 0 {
+    writeln!(ret, "#use \"h{}.h0\"", i - 1).unwrap();
+  }
+  writeln!(ret, "struct s{i} {{").unwrap();
+  writeln!(ret, "  int a;").unwrap();
+  writeln!(ret, "  bool b;").unwrap();
+  if i != 0 {
+    writeln!(ret, "  struct s{}* prev;", i - 1).unwrap();
+  }
+  writeln!(ret, "}};").unwrap();
+  writeln!(ret, "typedef struct s{i}* t{i};").unwrap();
+  for j in 0..fns {
+    writeln!(ret, "/* does thing {j} */").unwrap();
+    writeln!(ret, "int f{i}_{j}(int x, t{i} p);").unwrap();
+  }
+  ret
+}
+
+fn source(i: usize, fns: usize) -> String {
+  let mut ret = String::new();
+  writeln!(ret, "#use \"h{i}.h0\"").unwrap();
+  for j in 0..fns {
+    writeln!(ret).unwrap();
+    writeln!(ret, "int g{i}_{j}(int x, t{i} p) {{").unwrap();
+    writeln!(ret, "  int y = x + {j};").unwrap();
+    writeln!(ret, "  if (p != NULL && p->b) {{").unwrap();
+    writeln!(ret, "    y += p->a;").unwrap();
+    writeln!(ret, "  }}").unwrap();
+    writeln!(ret, "  while (y > 100) {{").unwrap();
+    writeln!(ret, "    y = y / 2;").unwrap();
+    writeln!(ret, "  }}").unwrap();
+    writeln!(ret, "  y = f{i}_{j}(y, p);").unwrap();
+    if i == 0 {
+      writeln!(ret, "  return y;").unwrap();
+    } else {
+      writeln!(ret, "  return f{}_{j}(y, NULL);", i - 1).unwrap();
+    }
+    writeln!(ret, "}}").unwrap();
+  }
+  ret
+}
+
+fn big(lines: usize) -> String {
+  let mut ret = String::new();
+  ret.push_str("int big(int n) {\n  int acc = 0;\n");
+  // each iteration adds 5 lines.
+  for k in 0..lines / 5 {
+    writeln!(ret, "  for (int i = 0; i < n; i++) {{").unwrap();
+    writeln!(ret, "    if (i % {} == 0) {{", k % 7 + 2).unwrap();
+    writeln!(ret, "      acc += i * {k};").unwrap();
+    writeln!(ret, "    }}").unwrap();
+    writeln!(ret, "  }}").unwrap();
+  }
+  ret.push_str("  return acc;\n}\n");
+  ret
+}
@@ -0,0 +1,178 @@
+//! Benchmarks for the analysis pipeline, on synthetic C0 workspaces.
+//!
+//! Run with `cargo xtask bench [FILTER]`. Only the benchmarks whose names
+//! contain the filter are run.
+
+#![deny(missing_debug_implementations)]
+#![deny(rust_2018_idioms)]
+
+mod gen;
+
+use analysis::{Db, Edit, Position, Range};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use uri_db::{Uri, UriDb};
+
+/// The number of files in each `#use` chain.
+const DEPTH: usize = 200;
+/// The number of fns in each file.
+const FNS: usize = 10;
+/// The number of lines in the big fn.
+const BIG_LINES: usize = 1000;
+
+/// How long to run each benchmark before sampling it, so e.g. caches and the
+/// allocator settle down.
+const WARMUP: Duration = Duration::from_millis(500);
+/// How long to spend sampling each benchmark, unless it hasn't got
+/// `MIN_SAMPLES` yet.
+const TIME: Duration = Duration::from_secs(2);
+/// How many samples to take for each benchmark, at least.
+const MIN_SAMPLES: usize = 10;
+/// How many samples to take for each benchmark, at most.
+const SAMPLES: usize = 100;
+
+fn main() {
+  let filter = std::env::args().nth(1).unwrap_or_default();
+  let ws = gen::workspace(DEPTH, FNS, BIG_LINES);
+  let lines: usize = ws.files.iter().map(|(_, c)| c.lines().count()).sum();
+  println!("{} files, {} lines", ws.files.len(), lines);
+  let mut b = Bencher { filter };
+  let big = ws.get("/big.c0");
+  b.run("lex", || lex::get(big));
+  b.run("parse", || parse::get(&lex::get(big).tokens));
+  b.run("lower", || {
+    lower::get(parse::get(&lex::get(big).tokens).root)
+  });
+  b.run("statics", || {
+    let lowered = lower::get(parse::get(&lex::get(big).tokens).root);
+    let mut uris = UriDb::default();
+    let file = statics::FileId::Source(uris.insert(uri("/big.c0")));
+    statics::get(
+      &mut statics::Cx::default(),
+      statics::Env::with_main(),
+      &mut statics::Bodies::default(),
+      file,
+      &lowered.root,
+    )
+  });
+  let files = || ws.files.iter().map(|(p, c)| (uri(p), c.clone()));
+  b.run("db_new", || Db::new(files()));
+  b.run("db_new_diagnostics", || Db::new(files()).all_diagnostics());
+  // toggles a digit in the big fn.
+  let big_uri = uri("/big.c0");
+  let big_pos = position(big, big.find("* 0;").unwrap() + 2);
+  let mut digit = false;
+  let mut db = Db::new(files());
+  db.all_diagnostics();
+  print!("{}", db.memory_usage());
+  b.run("edit_file", || {
+    digit = !digit;
+    db.edit_file(&big_uri, [edit(big_pos, if digit { "1" } else { "0" })]);
+  });
+  b.run("edit_file_diagnostics", || {
+    digit = !digit;
+    db.edit_file(&big_uri, [edit(big_pos, if digit { "1" } else { "0" })]);
+    db.all_diagnostics()
+  });
+  // hovers on the call in main, which depends on the whole `#use` chain.
+  let main_uri = uri("/main.c0");
+  let main = ws.get("/main.c0");
+  let main_pos = position(main, main.find("return g").unwrap() + 8);
+  db.hover(&main_uri, main_pos).expect("no hover");
+  b.run("hover", || db.hover(&main_uri, main_pos));
+  // toggles a digit in a comment at the bottom of a `#use` chain, which
+  // doesn't change what the files that use it can see.
+  let h0_uri = uri("/h0.h0");
+  let h0_pos = position(ws.get("/h0.h0"), "// rev ".len());
+  b.run("hover_after_edit", || {
+    digit = !digit;
+    db.edit_file(&h0_uri, [edit(h0_pos, if digit { "1" } else { "0" })]);
+    db.hover(&main_uri, main_pos)
+  });
+}
+
+#[derive(Debug)]
+struct Bencher {
+  filter: String,
+}
+
+impl Bencher {
+  /// Runs `f` repeatedly and prints some statistics about how long it took.
+  ///
+  /// We report the median and the interquartile range, not the mean, so a few
+  /// slow runs (e.g. from the OS scheduling something else) don't skew the
+  /// results. Those slow runs are counted as outliers.
+  fn run<F, T>(&mut self, name: &str, mut f: F)
+  where
+    F: FnMut() -> T,
+  {
+    if !name.contains(self.filter.as_str()) {
+      return;
+    }
+    let start = Instant::now();
+    loop {
+      black_box(f());
+      if start.elapsed() >= WARMUP {
+        break;
+      }
+    }
+    let mut samples = Vec::with_capacity(SAMPLES);
+    let start = Instant::now();
+    while samples.len() < SAMPLES
+      && (samples.len() < MIN_SAMPLES || start.elapsed() < TIME)
+    {
+      let now = Instant::now();
+      black_box(f());
+      samples.push(now.elapsed());
+    }
+    samples.sort_unstable();
+    let q1 = quantile(&samples, 1, 4);
+    let median = quantile(&samples, 1, 2);
+    let q3 = quantile(&samples, 3, 4);
+    let iqr = q3 - q1;
+    // Tukey's fences.
+    let low = q1.saturating_sub(iqr.mul_f64(1.5));
+    let high = q3 + iqr.mul_f64(1.5);
+    let outliers = samples.iter().filter(|&&x| x < low || x > high).count();
+    let spread = 100.0 * iqr.as_secs_f64() / median.as_secs_f64();
+    println!(
+      "{name:<24} median {median:>12?}  iqr {iqr:>12?} ({spread:>5.1}%)  \
+       ({} samples, {outliers} outliers)",
+      samples.len(),
+    );
+  }
+}
+
+/// Returns the `num / den` quantile of the sorted, non-empty samples, by the
+/// nearest rank.
+fn quantile(samples: &[Duration], num: usize, den: usize) -> Duration {
+  let idx = (samples.len() * num).div_ceil(den);
+  samples[idx.saturating_sub(1)]
+}
+
+fn uri(path: &str) -> Uri {
+  Uri::from_file_path(path).unwrap()
+}
+
+/// Returns the position of the byte offset in the text.
+fn position(text: &str, offset: usize) -> Position {
+  let before = &text[..offset];
+  let line = before.matches('\n').count();
+  let line_start = before.rfind('\n').map_or(0, |x| x + 1);
+  Position {
+    line: line as u32,
+    character: (offset - line_start) as u32,
+  }
+}
+
+/// Returns an edit replacing the one character at the position.
+fn edit(pos: Position, text: &str) -> Edit {
+  let end = Position {
+    line: pos.line,
+    character: pos.character + 1,
+  };
+  Edit {
+    text: text.to_owned(),
+    range: Some(Range { start: pos, end }),
+  }
+}
@@ -1,22 +1,77 @@
 use lsp_types::{
-  HoverProviderCapability, OneOf, ServerCapabilities,
+  CallHierarchyServerCapability, CompletionOptions, DiagnosticOptions,
+  DiagnosticServerCapabilities, FoldingRangeProviderCapability,
+  HoverProviderCapability, OneOf, SelectionRangeProviderCapability,
+  SemanticTokenModifier, SemanticTokenType, SemanticTokensFullOptions,
+  SemanticTokensLegend, SemanticTokensOptions,
+  SemanticTokensServerCapabilities, ServerCapabilities,
   TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
-  TextDocumentSyncSaveOptions,
+  TextDocumentSyncSaveOptions, WorkspaceFoldersServerCapabilities,
+  WorkspaceServerCapabilities,
 };
 
+/// The token types in the semantic tokens legend. Encoded tokens refer to
+/// these by index.
+pub(crate) const TOKEN_TYPES: [SemanticTokenType; 6] = [
+  SemanticTokenType::TYPE,
+  SemanticTokenType::STRUCT,
+  SemanticTokenType::FUNCTION,
+  SemanticTokenType::PARAMETER,
+  SemanticTokenType::VARIABLE,
+  SemanticTokenType::PROPERTY,
+];
+
+/// The token modifiers in the semantic tokens legend. Encoded tokens refer to
+/// these by bit index.
+pub(crate) const TOKEN_MODIFIERS: [SemanticTokenModifier; 1] =
+  [SemanticTokenModifier::DEFAULT_LIBRARY];
+
 pub(crate) fn get() -> ServerCapabilities {
   ServerCapabilities {
     text_document_sync: Some(TextDocumentSyncCapability::Options(
       TextDocumentSyncOptions {
-        open_close: Some(false),
+        open_close: Some(true),
         change: Some(TextDocumentSyncKind::INCREMENTAL),
         will_save: Some(false),
         will_save_wait_until: Some(false),
-        save: Some(TextDocumentSyncSaveOptions::Supported(false)),
+        save: Some(TextDocumentSyncSaveOptions::Supported(true)),
       },
     )),
     definition_provider: Some(OneOf::Left(true)),
     hover_provider: Some(HoverProviderCapability::Simple(true)),
+    completion_provider: Some(CompletionOptions::default()),
+    folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+    call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+    selection_range_provider: Some(SelectionRangeProviderCapability::Simple(
+      true,
+    )),
+    semantic_tokens_provider: Some(
+      SemanticTokensServerCapabilities::SemanticTokensOptions(
+        SemanticTokensOptions {
+          legend: SemanticTokensLegend {
+            token_types: TOKEN_TYPES.to_vec(),
+            token_modifiers: TOKEN_MODIFIERS.to_vec(),
+          },
+          range: Some(true),
+          full: Some(SemanticTokensFullOptions::Bool(true)),
+          ..SemanticTokensOptions::default()
+        },
+      ),
+    ),
+    diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
+      DiagnosticOptions {
+        inter_file_dependencies: true,
+        workspace_diagnostics: true,
+        ..DiagnosticOptions::default()
+      },
+    )),
+    workspace: Some(WorkspaceServerCapabilities {
+      workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+        supported: Some(true),
+        change_notifications: Some(OneOf::Left(true)),
+      }),
+      file_operations: None,
+    }),
     ..ServerCapabilities::default()
   }
 }
@@ -0,0 +1,124 @@
+//! The settings the client gives us, under the `c0ls` section.
+//!
+//! ```json
+//! {
+//!   "level": "c0",
+//!   "includeDirs": ["lib"],
+//!   "lints": { "suspicious": true },
+//!   "format": { "indentWidth": 4, "tabs": false },
+//!   "verbosity": "quiet"
+//! }
+//! ```
+//!
+//! Every setting is optional. Relative include dirs are relative to each
+//! workspace folder.
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// The name of the section of the client's settings for us.
+pub(crate) const SECTION: &str = "c0ls";
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct Settings {
+  level: Level,
+  pub(crate) include_dirs: Vec<String>,
+  lints: Lints,
+  format: Format,
+  verbosity: Verbosity,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Level {
+  C0,
+  #[default]
+  C1,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+struct Lints {
+  suspicious: bool,
+  style: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Format {
+  indent_width: usize,
+  tabs: bool,
+}
+
+impl Default for Format {
+  fn default() -> Self {
+    let style = analysis::Style::default();
+    Self {
+      indent_width: style.indent_width,
+      tabs: style.tabs,
+    }
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Verbosity {
+  Quiet,
+  #[default]
+  Normal,
+}
+
+/// Parses the settings. `null` means the defaults.
+pub(crate) fn get(value: serde_json::Value) -> Result<Settings, String> {
+  if value.is_null() {
+    return Ok(Settings::default());
+  }
+  serde_json::from_value(value).map_err(|e| e.to_string())
+}
+
+impl Settings {
+  /// Returns the config for the files in the folder, or for the files not in
+  /// any folder, which only get the absolute include dirs.
+  pub(crate) fn config(&self, folder: Option<&Path>) -> analysis::Config {
+    analysis::Config {
+      level: match self.level {
+        Level::C0 => analysis::Level::C0,
+        Level::C1 => analysis::Level::C1,
+      },
+      include_dirs: self
+        .include_paths(folder)
+        .filter_map(|dir| paths::from_dir(&dir))
+        .collect(),
+      lints: analysis::Lints {
+        suspicious: self.lints.suspicious,
+        style: self.lints.style,
+      },
+      format: analysis::Style {
+        indent_width: self.format.indent_width,
+        tabs: self.format.tabs,
+      },
+      verbosity: match self.verbosity {
+        Verbosity::Quiet => analysis::Verbosity::Quiet,
+        Verbosity::Normal => analysis::Verbosity::Normal,
+      },
+    }
+  }
+
+  /// Returns the include dirs for the files in the folder, as absolute paths
+  /// without any `.` or `..`.
+  pub(crate) fn include_paths<'a>(
+    &'a self,
+    folder: Option<&'a Path>,
+  ) -> impl Iterator<Item = PathBuf> + 'a {
+    self.include_dirs.iter().filter_map(move |dir| {
+      let dir = Path::new(dir);
+      let dir = if dir.is_absolute() {
+        dir.to_owned()
+      } else {
+        folder?.join(dir)
+      };
+      Some(paths::normalize(&dir))
+    })
+  }
+}
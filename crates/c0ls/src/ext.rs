@@ -0,0 +1,79 @@
+//! Extensions to the protocol, which our VS Code extension knows about.
+//!
+//! A client opts in to the `c0ls/status` notification by setting
+//! `experimental.statusNotification` in its capabilities.
+//!
+//! The `c0ls/syntaxTree`, `c0ls/hir`, and `c0ls/tokens` requests return what
+//! we make of a document, pretty-printed, or `null` if we don't know it.
+
+use lsp_types::notification::Notification;
+use lsp_types::request::Request;
+use lsp_types::TextDocumentIdentifier;
+use serde::{Deserialize, Serialize};
+
+/// The name of the experimental client capability for `c0ls/status`.
+pub(crate) const STATUS_CAPABILITY: &str = "statusNotification";
+
+/// Tells the client what the server is up to, e.g. to show in a status bar.
+#[derive(Debug)]
+pub(crate) enum Status {}
+
+impl Notification for Status {
+  type Params = StatusParams;
+  const METHOD: &'static str = "c0ls/status";
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StatusParams {
+  pub(crate) state: State,
+  /// more about the state, like which file is in a use cycle.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub(crate) message: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum State {
+  /// we're done, until something changes.
+  Idle,
+  /// we're loading or checking the files.
+  Analyzing,
+  /// a file transitively `#use`s itself, so we can't check the files.
+  Error,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InspectParams {
+  pub(crate) text_document: TextDocumentIdentifier,
+}
+
+/// The concrete syntax tree of a document.
+#[derive(Debug)]
+pub(crate) enum SyntaxTree {}
+
+impl Request for SyntaxTree {
+  type Params = InspectParams;
+  type Result = Option<String>;
+  const METHOD: &'static str = "c0ls/syntaxTree";
+}
+
+/// The HIR of a document, with the types we gave it.
+#[derive(Debug)]
+pub(crate) enum Hir {}
+
+impl Request for Hir {
+  type Params = InspectParams;
+  type Result = Option<String>;
+  const METHOD: &'static str = "c0ls/hir";
+}
+
+/// The tokens of a document.
+#[derive(Debug)]
+pub(crate) enum Tokens {}
+
+impl Request for Tokens {
+  type Params = InspectParams;
+  type Result = Option<String>;
+  const METHOD: &'static str = "c0ls/tokens";
+}
@@ -1,4 +1,6 @@
-use lsp_types::DiagnosticSeverity;
+use crate::capabilities::{TOKEN_MODIFIERS, TOKEN_TYPES};
+use analysis::{CompletionKind, FoldingRangeKind, SemanticTokenKind};
+use lsp_types::{DiagnosticSeverity, SemanticTokenModifier, SemanticTokenType};
 
 /// Crate-owned copy-paste of [`core::convert::From`].
 ///
@@ -68,21 +70,52 @@ impl CrateFrom<lsp_types::Location> for analysis::Location {
 
 impl CrateFrom<analysis::Hover> for lsp_types::Hover {
   fn from(val: analysis::Hover) -> Self {
+    let mut value = val.contents.to_string();
+    if let Some(doc) = val.doc {
+      value.push_str("\n\n---\n\n");
+      value.push_str(&doc);
+    }
     Self {
       range: Some(CrateFrom::from(val.range)),
       contents: lsp_types::HoverContents::Markup(lsp_types::MarkupContent {
         kind: lsp_types::MarkupKind::Markdown,
-        value: val.contents.to_string(),
+        value,
       }),
     }
   }
 }
 
+impl CrateFrom<analysis::Completion> for lsp_types::CompletionItem {
+  fn from(val: analysis::Completion) -> Self {
+    let kind = match val.kind {
+      CompletionKind::Var => lsp_types::CompletionItemKind::VARIABLE,
+      CompletionKind::Fn => lsp_types::CompletionItemKind::FUNCTION,
+      CompletionKind::TypeDef => lsp_types::CompletionItemKind::TYPE_PARAMETER,
+      CompletionKind::Struct => lsp_types::CompletionItemKind::STRUCT,
+    };
+    Self {
+      label: val.label,
+      kind: Some(kind),
+      detail: val.detail,
+      documentation: val.doc.map(|doc| {
+        lsp_types::Documentation::MarkupContent(lsp_types::MarkupContent {
+          kind: lsp_types::MarkupKind::Markdown,
+          value: doc,
+        })
+      }),
+      ..Self::default()
+    }
+  }
+}
+
 impl CrateFrom<analysis::Diagnostic> for lsp_types::Diagnostic {
   fn from(val: analysis::Diagnostic) -> Self {
     Self {
       range: CrateFrom::from(val.range),
-      severity: Some(DiagnosticSeverity::ERROR),
+      severity: Some(match val.severity {
+        analysis::Severity::Error => DiagnosticSeverity::ERROR,
+        analysis::Severity::Warning => DiagnosticSeverity::WARNING,
+      }),
       code: None,
       code_description: None,
       source: Some("c0ls".to_owned()),
@@ -93,3 +126,119 @@ impl CrateFrom<analysis::Diagnostic> for lsp_types::Diagnostic {
     }
   }
 }
+
+impl CrateFrom<Vec<analysis::SemanticToken>> for lsp_types::SemanticTokens {
+  fn from(mut val: Vec<analysis::SemanticToken>) -> Self {
+    val.sort_unstable();
+    let mut prev = analysis::Position {
+      line: 0,
+      character: 0,
+    };
+    let data = val
+      .into_iter()
+      .map(|tok| {
+        let start = tok.range.start;
+        let delta_start = if start.line == prev.line {
+          start.character - prev.character
+        } else {
+          start.character
+        };
+        let (ty, modifier) = token_type(tok.kind);
+        let ret = lsp_types::SemanticToken {
+          delta_line: start.line - prev.line,
+          delta_start,
+          length: tok.range.end.character - start.character,
+          token_type: index(&TOKEN_TYPES, &ty),
+          token_modifiers_bitset: modifier
+            .map_or(0, |m| 1 << index(&TOKEN_MODIFIERS, &m)),
+        };
+        prev = start;
+        ret
+      })
+      .collect();
+    Self {
+      result_id: None,
+      data,
+    }
+  }
+}
+
+impl CrateFrom<analysis::FoldingRange> for lsp_types::FoldingRange {
+  fn from(val: analysis::FoldingRange) -> Self {
+    let kind = match val.kind {
+      FoldingRangeKind::Region => lsp_types::FoldingRangeKind::Region,
+      FoldingRangeKind::Comment => lsp_types::FoldingRangeKind::Comment,
+      FoldingRangeKind::Imports => lsp_types::FoldingRangeKind::Imports,
+    };
+    Self {
+      start_line: val.range.start.line,
+      start_character: Some(val.range.start.character),
+      end_line: val.range.end.line,
+      end_character: Some(val.range.end.character),
+      kind: Some(kind),
+      ..Self::default()
+    }
+  }
+}
+
+impl CrateFrom<analysis::FnItem> for lsp_types::CallHierarchyItem {
+  fn from(val: analysis::FnItem) -> Self {
+    Self {
+      name: val.name,
+      kind: lsp_types::SymbolKind::FUNCTION,
+      tags: None,
+      detail: Some(val.signature),
+      uri: val.uri,
+      range: CrateFrom::from(val.range),
+      selection_range: CrateFrom::from(val.name_range),
+      data: None,
+    }
+  }
+}
+
+impl CrateFrom<lsp_types::CallHierarchyItem> for analysis::FnItem {
+  fn from(val: lsp_types::CallHierarchyItem) -> Self {
+    Self {
+      name: val.name,
+      signature: val.detail.unwrap_or_default(),
+      uri: val.uri,
+      range: CrateFrom::from(val.range),
+      name_range: CrateFrom::from(val.selection_range),
+    }
+  }
+}
+
+/// The ranges must go from innermost to outermost.
+impl CrateFrom<Vec<analysis::Range>> for lsp_types::SelectionRange {
+  fn from(val: Vec<analysis::Range>) -> Self {
+    let mut ret: Option<Self> = None;
+    for range in val.into_iter().rev() {
+      ret = Some(Self {
+        range: CrateFrom::from(range),
+        parent: ret.map(Box::new),
+      });
+    }
+    ret.unwrap_or_default()
+  }
+}
+
+fn token_type(
+  kind: SemanticTokenKind,
+) -> (SemanticTokenType, Option<SemanticTokenModifier>) {
+  match kind {
+    SemanticTokenKind::TypeDef => (SemanticTokenType::TYPE, None),
+    SemanticTokenKind::Struct => (SemanticTokenType::STRUCT, None),
+    SemanticTokenKind::Fn => (SemanticTokenType::FUNCTION, None),
+    SemanticTokenKind::Param => (SemanticTokenType::PARAMETER, None),
+    SemanticTokenKind::Local => (SemanticTokenType::VARIABLE, None),
+    SemanticTokenKind::Field => (SemanticTokenType::PROPERTY, None),
+    SemanticTokenKind::LibFn => (
+      SemanticTokenType::FUNCTION,
+      Some(SemanticTokenModifier::DEFAULT_LIBRARY),
+    ),
+  }
+}
+
+fn index<T: PartialEq>(xs: &[T], x: &T) -> u32 {
+  xs.iter().position(|y| y == x).expect("not in legend") as u32
+}
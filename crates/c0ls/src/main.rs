@@ -1,19 +1,150 @@
 //! A language server for C0.
+//!
+//! By default we talk LSP over stdio, but we can also connect to a client over
+//! TCP, or listen for any number of them, e.g. to attach a debugger to a
+//! long-lived server. See `help.txt` for the flags.
 
 mod capabilities;
+mod config;
+mod ext;
 mod from;
 mod main_loop;
+mod manifest;
+mod socket;
+mod workspace;
 mod wrapper;
 
-use lsp_server::Connection;
-use lsp_types::InitializeParams;
+use lsp_server::{Connection, ErrorCode};
+use lsp_types::{InitializeParams, InitializeResult};
+use pico_args::Arguments;
+
+/// How we talk to the client.
+#[derive(Debug)]
+enum Transport {
+  Stdio,
+  /// serve every client that connects to this address.
+  Listen(String),
+  /// connect to a client listening at this address.
+  Connect(String),
+}
+
+/// Returns how to talk to the client, or `None` if we should exit without
+/// doing so, e.g. after printing the help.
+fn get_transport() -> Result<Option<Transport>, String> {
+  let mut args = Arguments::from_env();
+  if args.contains(["-h", "--help"]) {
+    print!("{}", include_str!("help.txt"));
+    return Ok(None);
+  }
+  if args.contains(["-V", "--version"]) {
+    println!("c0ls {}", env!("CARGO_PKG_VERSION"));
+    return Ok(None);
+  }
+  let mut ret = Vec::new();
+  if args.contains("--stdio") {
+    ret.push(Transport::Stdio);
+  }
+  let port: Option<String> = args
+    .opt_value_from_str("--listen")
+    .map_err(|e| e.to_string())?;
+  if let Some(port) = port {
+    let addr = if port.contains(':') {
+      port
+    } else {
+      format!("127.0.0.1:{}", port)
+    };
+    ret.push(Transport::Listen(addr));
+  }
+  let addr: Option<String> = args
+    .opt_value_from_str("--connect")
+    .map_err(|e| e.to_string())?;
+  if let Some(addr) = addr {
+    ret.push(Transport::Connect(addr));
+  }
+  let rest = args.finish();
+  if !rest.is_empty() {
+    return Err(format!("unused arguments: {:?}", rest));
+  }
+  if ret.len() > 1 {
+    return Err("at most one of --stdio, --listen, --connect".to_owned());
+  }
+  Ok(Some(ret.pop().unwrap_or(Transport::Stdio)))
+}
 
 fn main() {
   env_logger::init();
-  let (conn, io_threads) = Connection::stdio();
-  let sc = serde_json::to_value(&capabilities::get()).unwrap();
-  let init = conn.initialize(sc).unwrap();
-  let init: InitializeParams = serde_json::from_value(init).unwrap();
-  main_loop::run(&conn, init);
-  io_threads.join().unwrap();
+  let transport = match get_transport() {
+    Ok(Some(x)) => x,
+    Ok(None) => return,
+    Err(e) => {
+      eprintln!("error: {}\nsee c0ls --help", e);
+      std::process::exit(2);
+    }
+  };
+  let (conn, io_threads) = match transport {
+    Transport::Stdio => Connection::stdio(),
+    Transport::Listen(addr) => {
+      if let Err(e) = socket::listen(&addr, serve) {
+        eprintln!("error: couldn't listen: {}", e);
+        std::process::exit(1);
+      }
+      return;
+    }
+    Transport::Connect(addr) => {
+      log::info!("connecting to {}", addr);
+      match Connection::connect(addr) {
+        Ok(x) => x,
+        Err(e) => {
+          eprintln!("error: couldn't connect: {}", e);
+          std::process::exit(1);
+        }
+      }
+    }
+  };
+  serve(conn);
+  if let Err(e) = io_threads.join() {
+    log::error!("couldn't stop: {}", e);
+  }
+}
+
+/// Serves the client until it exits. This drops the connection, since the
+/// thread writing to the client stops only once there is nothing left that
+/// could send it messages.
+fn serve(conn: Connection) {
+  if let Some(init) = initialize(&conn) {
+    main_loop::run(&conn, init);
+  }
+}
+
+/// Does the initialization handshake, returning what the client sent, or
+/// `None` if it went wrong.
+fn initialize(conn: &Connection) -> Option<InitializeParams> {
+  let (id, params) = match conn.initialize_start() {
+    Ok(x) => x,
+    Err(e) => {
+      log::error!("couldn't initialize: {}", e);
+      return None;
+    }
+  };
+  let init: InitializeParams = match serde_json::from_value(params) {
+    Ok(x) => x,
+    Err(e) => {
+      let msg = format!("bad params for initialize: {}", e);
+      log::error!("{}", msg);
+      let res = wrapper::error(id, ErrorCode::InvalidParams, msg);
+      // if this fails, there's no one to tell anyway.
+      let _ = conn.sender.send(res.into());
+      return None;
+    }
+  };
+  let result = InitializeResult {
+    capabilities: capabilities::get(),
+    server_info: None,
+  };
+  let result = serde_json::to_value(result).expect("couldn't make JSON");
+  if let Err(e) = conn.initialize_finish(id, result) {
+    log::error!("couldn't initialize: {}", e);
+    return None;
+  }
+  Some(init)
 }
@@ -1,66 +1,814 @@
-//! This is the only file (other than main.rs) that may have side effects.
+//! This is the only file (other than main.rs and socket.rs) that may have side
+//! effects.
+//!
+//! The main loop reads messages from the client and passes them on to a worker
+//! thread, which owns the `Db`s and handles the messages in order. So a request
+//! is answered using the files as of every edit received before it.
+//!
+//! The worker keeps the messages it has received but not yet handled in a
+//! queue, so that:
+//!
+//! - A `$/cancelRequest` for a request still in the queue removes it, and we
+//!   respond that it was cancelled.
+//! - We don't send diagnostics until there have been no edits for a bit, and
+//!   while we are sending them, we stop as soon as another message comes in, so
+//!   e.g. a hover request need not wait for diagnostics that are about to be
+//!   out of date anyway. We send the diagnostics again once the queue is empty.
+//! - We only send diagnostics for a file when they differ from what we last
+//!   sent for it, including clearing them when the file is deleted.
+//!
+//! If the client supports pulling diagnostics, we don't send them at all, and
+//! instead answer its requests for them. Each report has a result ID, the hash
+//! of the diagnostics, so if the client already has the current diagnostics, we
+//! tell it so instead of sending them again.
+//!
+//! Each workspace folder gets its own `Db`; see `workspace`. Its targets come
+//! from its manifest; see `manifest`. If a file outside every folder is opened,
+//! we also load the C0 files next to it, so that e.g. its `#use`s resolve even
+//! when the client opened no folder at all.
+//!
+//! The settings (see `config`) come from the initialization options at first.
+//! When the client says they changed, we ask it for them if it supports that,
+//! else we use the ones it sent along. The files in the include dirs are loaded
+//! into the `Db` of each folder that uses them, so changing the include dirs
+//! loads everything again.
+//!
+//! If the client can show progress, we show it for loading the files of the
+//! folders, and for checking all of them after that. If it wants to know (see
+//! `ext`), we also tell it whether we're analyzing, idle, or stuck on a use
+//! cycle.
+//!
+//! A bad message from the client, or a bug, shouldn't take down the server. We
+//! answer requests we don't know or whose params are bad with an error. If
+//! handling a message panics, we catch it and tell the client, and carry on with
+//! the next message.
 
+use crate::config::{self, Settings};
+use crate::ext::{self, State, StatusParams};
 use crate::from::CrateFrom;
-use crate::wrapper::{Handled, Notif, Req};
-use analysis::{Db, Edit, Update};
-use lsp_server::{Connection, Message, Response};
+use crate::manifest::{self, Manifest};
+use crate::workspace::Workspace;
+use crate::wrapper::{self, Handled, Notif, Req};
+use analysis::{Edit, Update};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::{Walk, WalkBuilder};
+use lsp_server::{Connection, ErrorCode, Message, RequestId, Response};
 use lsp_types::notification::{
-  DidChangeTextDocument, DidChangeWatchedFiles, PublishDiagnostics, ShowMessage,
+  Cancel, DidChangeConfiguration, DidChangeTextDocument, DidChangeWatchedFiles,
+  DidChangeWorkspaceFolders, DidCloseTextDocument, DidOpenTextDocument,
+  DidSaveTextDocument, Notification as _, Progress, PublishDiagnostics,
+  ShowMessage,
+};
+use lsp_types::request::{
+  CallHierarchyIncomingCalls, CallHierarchyOutgoingCalls, CallHierarchyPrepare,
+  Completion, DocumentDiagnosticRequest, FoldingRangeRequest, GotoDefinition,
+  HoverRequest, RegisterCapability, Request as _, SelectionRangeRequest,
+  SemanticTokensFullRequest, SemanticTokensRangeRequest, Shutdown,
+  WorkDoneProgressCreate, WorkspaceConfiguration, WorkspaceDiagnosticRequest,
 };
-use lsp_types::request::{GotoDefinition, HoverRequest};
 use lsp_types::{
-  FileChangeType, GotoDefinitionResponse, InitializeParams, MessageType,
-  PublishDiagnosticsParams, ShowMessageParams, Url,
+  CallHierarchyIncomingCall, CallHierarchyOutgoingCall, CompletionResponse,
+  ConfigurationItem, ConfigurationParams,
+  DidChangeWatchedFilesRegistrationOptions, DocumentDiagnosticReport,
+  DocumentDiagnosticReportKind, FileChangeType, FileSystemWatcher,
+  FullDocumentDiagnosticReport, GotoDefinitionResponse, InitializeParams,
+  MessageType, NumberOrString, ProgressParams, ProgressParamsValue,
+  ProgressToken, PublishDiagnosticsParams, Registration, RegistrationParams,
+  RelatedFullDocumentDiagnosticReport,
+  RelatedUnchangedDocumentDiagnosticReport, SemanticTokensRangeResult,
+  SemanticTokensResult, ShowMessageParams, UnchangedDocumentDiagnosticReport,
+  Url, WorkDoneProgress, WorkDoneProgressBegin, WorkDoneProgressCreateParams,
+  WorkDoneProgressEnd, WorkDoneProgressReport, WorkspaceDiagnosticReport,
+  WorkspaceDocumentDiagnosticReport, WorkspaceFullDocumentDiagnosticReport,
+  WorkspaceUnchangedDocumentDiagnosticReport,
 };
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs::read_to_string;
-use walkdir::WalkDir;
+use std::io::ErrorKind;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// How long to wait after an edit before sending diagnostics.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// The ID of the registration of the file watchers, and of the request for it.
+const WATCHERS: &str = "c0ls/watchers";
+
+/// The ID of the registration for changes to the settings, and of the request
+/// for it.
+const CONFIGURATION: &str = "c0ls/configuration";
+
+/// The ID of the requests for the settings.
+const SETTINGS: &str = "c0ls/settings";
+
+/// The prefix of the tokens of the progress we show, and of the IDs of the
+/// requests to create them.
+const PROGRESS: &str = "c0ls/progress";
 
 pub(crate) fn run(conn: &Connection, init: InitializeParams) {
   log::info!("starting");
-  let root = match init.root_uri {
-    None => {
-      show_error(conn, "cannot activate c0ls: no root".to_owned());
+  let client = Client {
+    conn,
+    gone: AtomicBool::new(false),
+  };
+  let conn = &client;
+  let folders: Vec<_> = match init.workspace_folders {
+    Some(folders) => folders.into_iter().map(|f| f.uri).collect(),
+    None => init.root_uri.into_iter().collect(),
+  };
+  if folders.is_empty() {
+    log::info!("no folders, so only analyzing open files");
+  }
+  let settings = match init.initialization_options {
+    Some(options) => get_settings(conn, options),
+    None => Settings::default(),
+  };
+  let workspace = init.capabilities.workspace.as_ref();
+  // if the client can tell us the settings, we ask it when they change.
+  let pull_settings = workspace.and_then(|x| x.configuration).unwrap_or(false);
+  if workspace
+    .and_then(|x| x.did_change_configuration.as_ref())
+    .and_then(|x| x.dynamic_registration)
+    .unwrap_or(false)
+  {
+    register_configuration(conn);
+  }
+  // if the client can pull diagnostics, it will.
+  let push_diagnostics = init
+    .capabilities
+    .text_document
+    .as_ref()
+    .and_then(|x| x.diagnostic.as_ref())
+    .is_none();
+  let work_done_progress = init
+    .capabilities
+    .window
+    .as_ref()
+    .and_then(|x| x.work_done_progress)
+    .unwrap_or(false);
+  let status = init
+    .capabilities
+    .experimental
+    .as_ref()
+    .and_then(|x| x.get(ext::STATUS_CAPABILITY))
+    .and_then(serde_json::Value::as_bool)
+    .unwrap_or(false);
+  let watch_files = workspace
+    .and_then(|x| x.did_change_watched_files.as_ref())
+    .and_then(|x| x.dynamic_registration)
+    .unwrap_or(false);
+  if watch_files {
+    register_watchers(conn);
+  } else {
+    log::warn!("client can't watch files, so we won't see changes on disk");
+  }
+  if pull_settings {
+    request_settings(conn);
+  }
+  let (sender, receiver) = mpsc::channel();
+  std::thread::scope(|s| {
+    let worker = s.spawn(|| {
+      let flags = Flags {
+        push_diagnostics,
+        pull_settings,
+        work_done_progress,
+        status,
+      };
+      Worker::new(conn, folders, settings, receiver, flags).run()
+    });
+    let mut shutdown = None;
+    for msg in conn.conn.receiver.iter() {
+      match msg {
+        Message::Request(req) if req.method == Shutdown::METHOD => {
+          shutdown = Some(req);
+          break;
+        }
+        msg => {
+          if sender.send(msg).is_err() {
+            log::error!("worker stopped");
+            break;
+          }
+        }
+      }
+    }
+    // lets the worker stop. it handles the messages it already got, but sends
+    // nothing else, like diagnostics or progress.
+    drop(sender);
+    if worker.join().is_err() {
+      log::error!("worker panicked");
+    }
+    // only now that the worker is done do we reply to the shutdown, since we
+    // may send nothing after that but the replies to requests.
+    if let Some(req) = shutdown {
+      log::info!("shutting down");
+      if let Err(e) = conn.conn.handle_shutdown(&req) {
+        log::error!("couldn't shut down: {}", e);
+      }
+    }
+  });
+}
+
+/// The connection to the client, which notes when we couldn't send it a
+/// message, after which it's no use going on.
+struct Client<'c> {
+  conn: &'c Connection,
+  gone: AtomicBool,
+}
+
+/// What the client can do, which changes how the worker talks to it.
+struct Flags {
+  push_diagnostics: bool,
+  pull_settings: bool,
+  work_done_progress: bool,
+  status: bool,
+}
+
+struct Worker<'c> {
+  conn: &'c Client<'c>,
+  receiver: mpsc::Receiver<Message>,
+  ws: Workspace,
+  settings: Settings,
+  /// whether to ask the client for the settings when they change, as opposed
+  /// to using the ones it sends with the notification.
+  pull_settings: bool,
+  /// the directories of the open files not in any folder, whose C0 files we
+  /// have loaded, so e.g. `#use`s in a lone open file still work.
+  loose_dirs: HashSet<PathBuf>,
+  /// the files each folder ignores.
+  excludes: HashMap<Url, Gitignore>,
+  /// the messages received but not yet handled.
+  queue: VecDeque<Message>,
+  /// whether to send diagnostics, as opposed to waiting for the client to ask
+  /// for them.
+  push_diagnostics: bool,
+  /// when to next send diagnostics, if they may have changed since we last
+  /// sent them.
+  diagnostics_at: Option<Instant>,
+  /// the versions of the open documents, as of their last edit.
+  versions: HashMap<Url, i32>,
+  /// the diagnostics we last sent, for the files that had any.
+  published: HashMap<Url, Vec<lsp_types::Diagnostic>>,
+  /// whether the client can show our progress.
+  work_done_progress: bool,
+  /// the number of progress tokens made so far.
+  progress_tokens: usize,
+  /// whether we have yet to check every file since loading them, which we show
+  /// progress for.
+  check_all: bool,
+  /// the progress of checking every file, once we've started.
+  checking: Option<ProgressToken>,
+  /// whether to send the status.
+  send_status: bool,
+  /// the status we last sent.
+  status: Option<StatusParams>,
+}
+
+impl<'c> Worker<'c> {
+  fn new(
+    conn: &'c Client<'c>,
+    folders: Vec<Url>,
+    settings: Settings,
+    receiver: mpsc::Receiver<Message>,
+    flags: Flags,
+  ) -> Self {
+    let Flags {
+      push_diagnostics,
+      pull_settings,
+      work_done_progress,
+      status,
+    } = flags;
+    let mut ret = Self {
+      conn,
+      receiver,
+      ws: Workspace::new(Vec::new(), &settings),
+      settings,
+      pull_settings,
+      loose_dirs: HashSet::new(),
+      excludes: HashMap::new(),
+      queue: VecDeque::new(),
+      push_diagnostics,
+      diagnostics_at: push_diagnostics.then(Instant::now),
+      versions: HashMap::new(),
+      published: HashMap::new(),
+      work_done_progress,
+      progress_tokens: 0,
+      check_all: false,
+      checking: None,
+      send_status: status,
+      status: None,
+    };
+    ret.set_folders(folders);
+    ret
+  }
+
+  /// Sets the workspace folders, and loads all their files from disk again,
+  /// keeping the contents of the open files.
+  fn set_folders(&mut self, folders: Vec<Url>) {
+    let open: Vec<_> = self
+      .ws
+      .dbs()
+      .flat_map(|db| db.open_files())
+      .map(|(uri, contents)| (uri.clone(), contents.to_owned()))
+      .collect();
+    self.ws = Workspace::new(folders, &self.settings);
+    self.loose_dirs.clear();
+    self.excludes.clear();
+    let folders: Vec<_> = self.ws.folders().cloned().collect();
+    self.set_status(State::Analyzing, None);
+    let loading = self.begin_progress("Loading", false);
+    let mut found = 0usize;
+    let mut files = Vec::new();
+    let mut targets = Vec::new();
+    for folder in folders {
+      // a folder not on disk only has the files open in the client.
+      let dir = match paths::to_path(&folder) {
+        Some(x) => x,
+        None => continue,
+      };
+      let manifest = read_manifest(self.conn, &dir);
+      let excludes = get_excludes(self.conn, &dir, &manifest.exclude);
+      let mut folder_walk = walk(&dir);
+      let filter = excludes.clone();
+      folder_walk.filter_entry(move |entry| {
+        let is_dir = entry.file_type().is_some_and(|x| x.is_dir());
+        !is_excluded(&filter, entry.path(), is_dir)
+      });
+      let folder_files: Vec<_> =
+        get_files(self.conn, folder_walk.build()).collect();
+      // the include dirs in the folder were just loaded.
+      let include_files: Vec<_> = self
+        .ws
+        .includes(&folder)
+        .iter()
+        .flat_map(|inc| get_files(self.conn, walk(inc).build()))
+        .collect();
+      found += folder_files.len() + include_files.len();
+      files.extend(folder_files);
+      files.extend(include_files);
+      if let Some(token) = &loading {
+        let msg = format!("{} files found", found);
+        report_progress(self.conn, token, msg, None);
+      }
+      self.excludes.insert(folder.clone(), excludes);
+      targets.push((folder, manifest.targets));
+    }
+    self
+      .ws
+      .update_files(files.into_iter().map(|(u, c)| Update::Create(u, c)));
+    for (folder, targets) in targets {
+      self.ws.set_targets(&folder, targets);
+    }
+    for (uri, contents) in open {
+      self.open_file(uri, contents);
+    }
+    if let Some(token) = loading {
+      end_progress(self.conn, token, format!("{} files found", found));
+    }
+    // if we push diagnostics, we check every file soon. else, the client will
+    // ask for them, so as far as we know, we're done.
+    if self.push_diagnostics {
+      self.check_all = true;
+    } else {
+      self.update_status();
+    }
+  }
+
+  /// Sets the settings, loading everything again if the include dirs changed.
+  fn set_settings(&mut self, settings: Settings) {
+    if self.settings == settings {
       return;
     }
-    Some(x) => x,
-  };
-  let mut db = Db::new(get_initial_files(conn, &root));
-  send_all_diagnostics(conn, &db);
-  for msg in conn.receiver.iter() {
+    let reload = self.settings.include_dirs != settings.include_dirs;
+    self.settings = settings;
+    if reload {
+      let folders = self.ws.folders().cloned().collect();
+      self.set_folders(folders);
+    } else {
+      self.ws.set_settings(&self.settings);
+    }
+  }
+
+  /// Returns whether the file is the manifest of a folder.
+  fn is_manifest(&self, uri: &Url) -> bool {
+    self.ws.folder(uri).and_then(manifest::uri).as_ref() == Some(uri)
+  }
+
+  /// Returns whether the folder the file is in ignores it.
+  fn is_excluded(&self, uri: &Url) -> bool {
+    let excludes = self.ws.folder(uri).and_then(|f| self.excludes.get(f));
+    match (excludes, paths::to_path(uri)) {
+      (Some(excludes), Some(path)) => is_excluded(excludes, &path, false),
+      _ => false,
+    }
+  }
+
+  fn open_file(&mut self, uri: Url, contents: String) {
+    if self.ws.is_loose(&uri) {
+      let path = paths::to_path(&uri);
+      if let Some(dir) = path.as_deref().and_then(Path::parent) {
+        if self.loose_dirs.insert(dir.to_owned()) {
+          let files =
+            get_files(self.conn, walk(dir).max_depth(Some(1)).build());
+          let files: Vec<_> = files.collect();
+          self
+            .ws
+            .update_files(files.into_iter().map(|(u, c)| Update::Create(u, c)));
+        }
+      }
+      let include_dirs: Vec<_> = self
+        .settings
+        .include_paths(None)
+        .filter(|inc| self.loose_dirs.insert(inc.clone()))
+        .collect();
+      for inc in include_dirs {
+        let files: Vec<_> = get_files(self.conn, walk(&inc).build()).collect();
+        self
+          .ws
+          .update_files(files.into_iter().map(|(u, c)| Update::Create(u, c)));
+      }
+    }
+    self.ws.get_mut(&uri).open_file(uri, contents);
+  }
+
+  fn run(mut self) {
+    loop {
+      if self.conn.gone.load(Ordering::Relaxed) {
+        log::error!("client is gone, so stopping");
+        return;
+      }
+      while let Ok(msg) = self.receiver.try_recv() {
+        push(self.conn, &mut self.queue, msg);
+      }
+      if let Some(msg) = self.queue.pop_front() {
+        self.handle(msg);
+        continue;
+      }
+      let msg = match self.diagnostics_at {
+        None => match self.receiver.recv() {
+          Ok(x) => x,
+          Err(_) => return,
+        },
+        Some(at) => {
+          match self
+            .receiver
+            .recv_timeout(at.saturating_duration_since(Instant::now()))
+          {
+            Ok(x) => x,
+            Err(RecvTimeoutError::Timeout) => {
+              if let Err(msg) = catch_panic(|| self.send_all_diagnostics()) {
+                show_error(self.conn, format!("diagnostics panicked: {}", msg));
+                // we won't try again until something changes.
+                self.update_status();
+              }
+              continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => return,
+          }
+        }
+      };
+      push(self.conn, &mut self.queue, msg);
+    }
+  }
+
+  fn handle(&mut self, msg: Message) {
     match msg {
       Message::Request(req) => {
-        if conn
-          .handle_shutdown(&req)
-          .expect("couldn't handle shutdown")
+        let id = req.id.clone();
+        let (ws, versions) = (&self.ws, &self.versions);
+        let res = match catch_panic(|| handle_req(ws, versions, Req::new(req)))
         {
-          log::info!("shutting down");
+          Ok(Ok(req)) => {
+            log::warn!("unknown request: {}", req.method());
+            let req = req.into_inner();
+            let msg = format!("unknown request: {}", req.method);
+            wrapper::error(req.id, ErrorCode::MethodNotFound, msg)
+          }
+          Ok(Err(res)) => res,
+          Err(msg) => {
+            log::error!("request {} panicked: {}", id, msg);
+            let msg = format!("panicked: {}", msg);
+            wrapper::error(id, ErrorCode::InternalError, msg)
+          }
+        };
+        send(self.conn, res.into());
+      }
+      // the client may reject our requests, like to create progress or watch
+      // files, so that's no reason to bother the user.
+      Message::Response(res) => match res.error {
+        Some(e) => log::warn!("request {} failed: {}", res.id, e.message),
+        None => {
+          log::info!("request {} succeeded", res.id);
+          if res.id == RequestId::from(SETTINGS.to_owned()) {
+            // we asked for just the one section.
+            let value = match res.result {
+              Some(serde_json::Value::Array(mut xs)) if xs.len() == 1 => {
+                xs.pop().expect("just checked len")
+              }
+              _ => serde_json::Value::Null,
+            };
+            let settings = get_settings(self.conn, value);
+            self.set_settings(settings);
+            if self.push_diagnostics {
+              self.schedule_diagnostics(Instant::now());
+            }
+          }
+        }
+      },
+      Message::Notification(notif) => {
+        let method = notif.method.clone();
+        match catch_panic(|| self.handle_notif(Notif::new(notif))) {
+          Ok(Ok(notif)) => {
+            log::warn!("ignoring notification: {}", notif.method())
+          }
+          Ok(Err(Handled)) => {
+            if self.push_diagnostics {
+              self.schedule_diagnostics(Instant::now() + DEBOUNCE);
+            } else {
+              self.update_status();
+            }
+          }
+          Err(msg) => show_error(
+            self.conn,
+            format!("notification {} panicked: {}", method, msg),
+          ),
+        }
+      }
+    }
+  }
+
+  fn handle_notif(&mut self, notif: Notif) -> Result<Notif, Handled> {
+    let conn = self.conn;
+    notif
+      .handle::<DidChangeConfiguration, _>(|params| {
+        log::info!("configuration changed");
+        if self.pull_settings {
+          request_settings(conn);
+          return;
+        }
+        let mut value = params.settings;
+        if let Some(section) = value.get_mut(config::SECTION) {
+          value = section.take();
+        }
+        let settings = get_settings(conn, value);
+        self.set_settings(settings);
+      })?
+      .handle::<DidChangeWatchedFiles, _>(|params| {
+        log::info!("watched files changed");
+        let (manifests, changes): (Vec<_>, Vec<_>) = params
+          .changes
+          .into_iter()
+          .partition(|change| self.is_manifest(&change.uri));
+        if !manifests.is_empty() {
+          // the excludes may have changed, so load everything again.
+          let folders = self.ws.folders().cloned().collect();
+          self.set_folders(folders);
           return;
         }
-        match handle_req(&db, Req::new(req)) {
-          Ok(req) => log::warn!("ignoring request: {}", req.method()),
-          Err(res) => conn
-            .sender
-            .send(res.into())
-            .expect("couldn't send response"),
+        let changes: Vec<_> = changes
+          .into_iter()
+          .filter(|change| {
+            change.typ == FileChangeType::DELETED
+              || !self.is_excluded(&change.uri)
+          })
+          .collect();
+        // this only changes the contents of files not open in the client.
+        let updates =
+          changes.into_iter().filter_map(|change| match change.typ {
+            FileChangeType::CREATED | FileChangeType::CHANGED => {
+              let path = paths::to_path(&change.uri)?;
+              match read_to_string(&path) {
+                Ok(contents) => Some(Update::Create(change.uri, contents)),
+                Err(e) => {
+                  show_error(conn, format!("{}: {}", path.display(), e));
+                  None
+                }
+              }
+            }
+            FileChangeType::DELETED => Some(Update::Delete(change.uri)),
+            _ => None,
+          });
+        self.ws.update_files(updates);
+      })?
+      .handle::<DidChangeWorkspaceFolders, _>(|params| {
+        log::info!("workspace folders changed");
+        let event = params.event;
+        let removed: HashSet<_> =
+          event.removed.into_iter().map(|f| f.uri).collect();
+        let folders: Vec<_> = self
+          .ws
+          .folders()
+          .filter(|&f| !removed.contains(f))
+          .cloned()
+          .chain(event.added.into_iter().map(|f| f.uri))
+          .collect();
+        self.set_folders(folders);
+      })?
+      .handle::<DidOpenTextDocument, _>(|params| {
+        log::info!("did open a text document");
+        let doc = params.text_document;
+        self.versions.insert(doc.uri.clone(), doc.version);
+        self.open_file(doc.uri, doc.text);
+      })?
+      .handle::<DidCloseTextDocument, _>(|params| {
+        log::info!("did close a text document");
+        let uri = params.text_document.uri;
+        // the contents now come from the disk, not the client.
+        self.versions.remove(&uri);
+        self.ws.get_mut(&uri).close_file(&uri);
+      })?
+      .handle::<DidSaveTextDocument, _>(|params| {
+        log::info!("did save a text document");
+        let uri = params.text_document.uri;
+        self.ws.get_mut(&uri).save_file(&uri);
+      })?
+      .handle::<DidChangeTextDocument, _>(|params| {
+        log::info!("did change a text document");
+        let doc = params.text_document;
+        self.versions.insert(doc.uri.clone(), doc.version);
+        self.ws.get_mut(&doc.uri).edit_file(
+          &doc.uri,
+          params.content_changes.into_iter().map(|edit| Edit {
+            range: edit.range.map(CrateFrom::from),
+            text: edit.text,
+          }),
+        );
+      })
+  }
+
+  /// Sends diagnostics at the given time, and until then, tells the client
+  /// we're analyzing. After sending them, the status goes back to idle (or
+  /// error).
+  fn schedule_diagnostics(&mut self, at: Instant) {
+    self.diagnostics_at = Some(at);
+    self.set_status(State::Analyzing, None);
+  }
+
+  /// Sends diagnostics for every file whose diagnostics changed since we last
+  /// sent them, unless a message comes in first, in which case we try again
+  /// later.
+  fn send_all_diagnostics(&mut self) {
+    self.diagnostics_at = None;
+    if self.check_all && self.checking.is_none() {
+      self.checking = self.begin_progress("Checking", true);
+    }
+    let total: usize =
+      self.ws.dbs().map(|db| db.diagnostics_by_file().len()).sum();
+    let mut checked = 0usize;
+    let mut percentage = 0u32;
+    let mut seen = HashSet::new();
+    let all = self.ws.dbs().flat_map(|db| db.diagnostics_by_file());
+    for (uri, diagnostics, _) in all {
+      let diagnostics: Vec<_> =
+        diagnostics.into_iter().map(CrateFrom::from).collect();
+      let old = self.published.get(&uri).map_or(&[][..], Vec::as_slice);
+      if old != diagnostics.as_slice() {
+        let version = self.versions.get(&uri).copied();
+        send_diagnostics(self.conn, uri.clone(), diagnostics.clone(), version);
+        if diagnostics.is_empty() {
+          self.published.remove(&uri);
+        } else {
+          self.published.insert(uri.clone(), diagnostics);
         }
       }
-      Message::Response(res) => log::warn!("ignoring response: {:?}", res),
-      Message::Notification(notif) => {
-        match handle_notif(conn, &mut db, Notif::new(notif)) {
-          Ok(notif) => log::warn!("ignoring notification: {}", notif.method()),
-          Err(Handled) => {}
+      seen.insert(uri);
+      checked += 1;
+      if let Some(token) = &self.checking {
+        let new = (checked * 100 / total) as u32;
+        if new != percentage {
+          percentage = new;
+          let msg = format!("{}/{} files", checked, total);
+          report_progress(self.conn, token, msg, Some(percentage));
         }
       }
+      while let Ok(msg) = self.receiver.try_recv() {
+        push(self.conn, &mut self.queue, msg);
+      }
+      if !self.queue.is_empty() {
+        // we're still analyzing, since we'll try again soon.
+        self.diagnostics_at = Some(Instant::now());
+        return;
+      }
+    }
+    // clear the diagnostics of files that were deleted.
+    let conn = self.conn;
+    self.published.retain(|uri, _| {
+      let keep = seen.contains(uri);
+      if !keep {
+        send_diagnostics(conn, uri.clone(), Vec::new(), None);
+      }
+      keep
+    });
+    if let Some(token) = self.checking.take() {
+      end_progress(self.conn, token, format!("{} files checked", total));
     }
+    self.check_all = false;
+    self.update_status();
+  }
+
+  /// Sends the status, unless it's what we last sent: an error if there is a
+  /// use cycle, else idle.
+  fn update_status(&mut self) {
+    if !self.send_status {
+      return;
+    }
+    let cycle = self.ws.dbs().find_map(|db| db.use_cycle());
+    match cycle {
+      Some(uri) => {
+        let file = paths::to_path(&uri)
+          .map_or_else(|| uri.to_string(), |p| p.display().to_string());
+        let msg = format!("{} transitively `#use`s itself", file);
+        self.set_status(State::Error, Some(msg));
+      }
+      None => self.set_status(State::Idle, None),
+    }
+  }
+
+  fn set_status(&mut self, state: State, message: Option<String>) {
+    if !self.send_status {
+      return;
+    }
+    let params = StatusParams { state, message };
+    if self.status.as_ref() == Some(&params) {
+      return;
+    }
+    send(self.conn, mk_notif::<ext::Status>(params.clone()));
+    self.status = Some(params);
+  }
+
+  /// Starts showing progress in the client, if it can, returning the token for
+  /// the rest of the progress.
+  fn begin_progress(
+    &mut self,
+    title: &str,
+    percentage: bool,
+  ) -> Option<ProgressToken> {
+    if !self.work_done_progress {
+      return None;
+    }
+    self.progress_tokens += 1;
+    let name = format!("{}/{}", PROGRESS, self.progress_tokens);
+    let token = ProgressToken::String(name.clone());
+    let params = WorkDoneProgressCreateParams {
+      token: token.clone(),
+    };
+    let id = RequestId::from(name);
+    send(self.conn, mk_req::<WorkDoneProgressCreate>(id, params));
+    let begin = WorkDoneProgressBegin {
+      title: title.to_owned(),
+      cancellable: Some(false),
+      message: None,
+      percentage: percentage.then_some(0),
+    };
+    send_progress(self.conn, token.clone(), WorkDoneProgress::Begin(begin));
+    Some(token)
   }
 }
 
-fn handle_req(db: &Db, req: Req) -> Result<Req, Response> {
+/// Adds the message to the end of the queue, unless it cancels a request in the
+/// queue, in which case we remove that request and respond that it was
+/// cancelled.
+fn push(conn: &Client<'_>, queue: &mut VecDeque<Message>, msg: Message) {
+  let notif = match msg {
+    Message::Notification(notif) => notif,
+    msg => {
+      queue.push_back(msg);
+      return;
+    }
+  };
+  let res = Notif::new(notif).handle::<Cancel, _>(|params| {
+    let id = match params.id {
+      NumberOrString::Number(n) => RequestId::from(n),
+      NumberOrString::String(s) => RequestId::from(s),
+    };
+    let idx = queue
+      .iter()
+      .position(|msg| matches!(msg, Message::Request(req) if req.id == id));
+    if let Some(idx) = idx {
+      log::info!("cancelled request: {}", id);
+      queue.remove(idx);
+      let code = ErrorCode::RequestCanceled;
+      let res = wrapper::error(id, code, "cancelled".to_owned());
+      send(conn, res.into());
+    }
+  });
+  if let Ok(notif) = res {
+    queue.push_back(Message::Notification(notif.into_inner()));
+  }
+}
+
+fn handle_req(
+  ws: &Workspace,
+  versions: &HashMap<Url, i32>,
+  req: Req,
+) -> Result<Req, Response> {
   req
     .handle::<GotoDefinition, _>(|_, params| {
       log::info!("goto def");
       let params = params.text_document_position_params;
-      let loc = db.go_to_def(
+      let loc = ws.get(&params.text_document.uri).go_to_def(
         &params.text_document.uri,
         CrateFrom::from(params.position),
       )?;
@@ -69,97 +817,469 @@ fn handle_req(db: &Db, req: Req) -> Result<Req, Response> {
     .handle::<HoverRequest, _>(|_, params| {
       log::info!("hover");
       let params = params.text_document_position_params;
-      db.hover(&params.text_document.uri, CrateFrom::from(params.position))
-        .map(CrateFrom::from)
-    })
-}
-
-fn handle_notif(
-  conn: &Connection,
-  db: &mut Db,
-  notif: Notif,
-) -> Result<Notif, Handled> {
-  notif
-    .handle::<DidChangeWatchedFiles, _>(|params| {
-      log::info!("watched files changed");
-      db.update_files(params.changes.into_iter().filter_map(|change| {
-        match change.typ {
-          FileChangeType::CREATED | FileChangeType::CHANGED => {
-            let path = change.uri.path();
-            match read_to_string(path) {
-              Ok(contents) => Some(Update::Create(change.uri, contents)),
-              Err(e) => {
-                show_error(conn, format!("{}: {}", path, e));
-                None
-              }
-            }
+      let uri = &params.text_document.uri;
+      let hover = ws.get(uri).hover(uri, CrateFrom::from(params.position))?;
+      Some(CrateFrom::from(hover))
+    })?
+    .handle::<Completion, _>(|_, params| {
+      log::info!("completion");
+      let params = params.text_document_position;
+      let completions = ws.get(&params.text_document.uri).completions(
+        &params.text_document.uri,
+        CrateFrom::from(params.position),
+      )?;
+      Some(CompletionResponse::Array(
+        completions.into_iter().map(CrateFrom::from).collect(),
+      ))
+    })?
+    .handle::<SemanticTokensFullRequest, _>(|_, params| {
+      log::info!("semantic tokens full");
+      let uri = &params.text_document.uri;
+      let tokens = ws.get(uri).semantic_tokens(uri, None)?;
+      Some(SemanticTokensResult::Tokens(CrateFrom::from(tokens)))
+    })?
+    .handle::<SemanticTokensRangeRequest, _>(|_, params| {
+      log::info!("semantic tokens range");
+      let tokens = ws.get(&params.text_document.uri).semantic_tokens(
+        &params.text_document.uri,
+        Some(CrateFrom::from(params.range)),
+      )?;
+      Some(SemanticTokensRangeResult::Tokens(CrateFrom::from(tokens)))
+    })?
+    .handle::<FoldingRangeRequest, _>(|_, params| {
+      log::info!("folding range");
+      let uri = &params.text_document.uri;
+      let ranges = ws.get(uri).folding_ranges(uri)?;
+      Some(ranges.into_iter().map(CrateFrom::from).collect())
+    })?
+    .handle::<SelectionRangeRequest, _>(|_, params| {
+      log::info!("selection range");
+      let positions: Vec<_> =
+        params.positions.into_iter().map(CrateFrom::from).collect();
+      let uri = &params.text_document.uri;
+      let ranges = ws.get(uri).selection_ranges(uri, &positions)?;
+      Some(ranges.into_iter().map(CrateFrom::from).collect())
+    })?
+    .handle::<CallHierarchyPrepare, _>(|_, params| {
+      log::info!("call hierarchy prepare");
+      let params = params.text_document_position_params;
+      let item = ws
+        .get(&params.text_document.uri)
+        .fn_item(&params.text_document.uri, CrateFrom::from(params.position))?;
+      Some(vec![CrateFrom::from(item)])
+    })?
+    .handle::<CallHierarchyIncomingCalls, _>(|_, params| {
+      log::info!("call hierarchy incoming calls");
+      let db = ws.get(&params.item.uri);
+      let calls = db.incoming_calls(&CrateFrom::from(params.item));
+      let calls =
+        calls
+          .into_iter()
+          .map(|(item, ranges)| CallHierarchyIncomingCall {
+            from: CrateFrom::from(item),
+            from_ranges: ranges.into_iter().map(CrateFrom::from).collect(),
+          });
+      Some(calls.collect())
+    })?
+    .handle::<CallHierarchyOutgoingCalls, _>(|_, params| {
+      log::info!("call hierarchy outgoing calls");
+      let db = ws.get(&params.item.uri);
+      let calls = db.outgoing_calls(&CrateFrom::from(params.item));
+      let calls =
+        calls
+          .into_iter()
+          .map(|(item, ranges)| CallHierarchyOutgoingCall {
+            to: CrateFrom::from(item),
+            from_ranges: ranges.into_iter().map(CrateFrom::from).collect(),
+          });
+      Some(calls.collect())
+    })?
+    .handle::<DocumentDiagnosticRequest, _>(|_, params| {
+      log::info!("document diagnostic");
+      // files we don't know about have no diagnostics.
+      let uri = &params.text_document.uri;
+      let (diagnostics, hash) =
+        ws.get(uri).file_diagnostics(uri).unwrap_or_default();
+      let previous = params.previous_result_id;
+      let report =
+        match diagnostic_report(diagnostics, hash, previous.as_deref()) {
+          DocumentDiagnosticReportKind::Full(report) => {
+            DocumentDiagnosticReport::Full(
+              RelatedFullDocumentDiagnosticReport {
+                related_documents: None,
+                full_document_diagnostic_report: report,
+              },
+            )
+          }
+          DocumentDiagnosticReportKind::Unchanged(report) => {
+            DocumentDiagnosticReport::Unchanged(
+              RelatedUnchangedDocumentDiagnosticReport {
+                related_documents: None,
+                unchanged_document_diagnostic_report: report,
+              },
+            )
+          }
+        };
+      report.into()
+    })?
+    .handle::<WorkspaceDiagnosticRequest, _>(|_, params| {
+      log::info!("workspace diagnostic");
+      let previous: HashMap<_, _> = params
+        .previous_result_ids
+        .into_iter()
+        .map(|x| (x.uri, x.value))
+        .collect();
+      let all = ws.dbs().flat_map(|db| db.diagnostics_by_file());
+      let items = all.map(|(uri, diagnostics, hash)| {
+        let version = versions.get(&uri).map(|&x| x.into());
+        let previous = previous.get(&uri).map(String::as_str);
+        match diagnostic_report(diagnostics, hash, previous) {
+          DocumentDiagnosticReportKind::Full(report) => {
+            WorkspaceDocumentDiagnosticReport::Full(
+              WorkspaceFullDocumentDiagnosticReport {
+                uri,
+                version,
+                full_document_diagnostic_report: report,
+              },
+            )
+          }
+          DocumentDiagnosticReportKind::Unchanged(report) => {
+            WorkspaceDocumentDiagnosticReport::Unchanged(
+              WorkspaceUnchangedDocumentDiagnosticReport {
+                uri,
+                version,
+                unchanged_document_diagnostic_report: report,
+              },
+            )
           }
-          FileChangeType::DELETED => Some(Update::Delete(change.uri)),
-          _ => None,
         }
-      }));
-      send_all_diagnostics(conn, db);
+      });
+      WorkspaceDiagnosticReport {
+        items: items.collect(),
+      }
+      .into()
     })?
-    .handle::<DidChangeTextDocument, _>(|params| {
-      log::info!("did change a text document");
-      db.edit_file(
-        &params.text_document.uri,
-        params.content_changes.into_iter().map(|edit| Edit {
-          range: edit.range.map(CrateFrom::from),
-          text: edit.text,
-        }),
-      );
-      send_all_diagnostics(conn, db);
+    .handle::<ext::SyntaxTree, _>(|_, params| {
+      log::info!("syntax tree");
+      let uri = params.text_document.uri;
+      ws.get(&uri).syntax_tree(&uri)
+    })?
+    .handle::<ext::Hir, _>(|_, params| {
+      log::info!("hir");
+      let uri = params.text_document.uri;
+      ws.get(&uri).hir(&uri)
+    })?
+    .handle::<ext::Tokens, _>(|_, params| {
+      log::info!("tokens");
+      let uri = params.text_document.uri;
+      ws.get(&uri).tokens(&uri)
     })
 }
 
-fn get_initial_files<'c>(
-  conn: &'c Connection,
-  root: &Url,
-) -> impl Iterator<Item = (Url, String)> + 'c {
-  WalkDir::new(root.path())
-    .into_iter()
-    .filter_map(move |entry| {
-      let entry = match entry {
-        Ok(x) => x,
-        Err(e) => {
-          show_error(conn, e.to_string());
-          return None;
-        }
+/// Returns a report of the diagnostics with the given hash, which is unchanged
+/// if the result ID of the previous report is still current.
+fn diagnostic_report(
+  diagnostics: Vec<analysis::Diagnostic>,
+  hash: u64,
+  previous_result_id: Option<&str>,
+) -> DocumentDiagnosticReportKind {
+  let result_id = hash.to_string();
+  if previous_result_id == Some(result_id.as_str()) {
+    UnchangedDocumentDiagnosticReport { result_id }.into()
+  } else {
+    FullDocumentDiagnosticReport {
+      result_id: Some(result_id),
+      items: diagnostics.into_iter().map(CrateFrom::from).collect(),
+    }
+    .into()
+  }
+}
+
+/// Parses the settings, or returns the defaults if they're bad.
+fn get_settings(conn: &Client<'_>, value: serde_json::Value) -> Settings {
+  match config::get(value) {
+    Ok(x) => x,
+    Err(e) => {
+      show_error(conn, format!("bad settings: {}", e));
+      Settings::default()
+    }
+  }
+}
+
+/// Returns the manifest in the directory, or an empty one if there is none.
+fn read_manifest(conn: &Client<'_>, dir: &Path) -> Manifest {
+  let path = dir.join(manifest::FILE_NAME);
+  let ret = match read_to_string(&path) {
+    Ok(contents) => match manifest::get(dir, &contents) {
+      Ok(x) => x,
+      Err(e) => {
+        show_error(conn, format!("{}: {}", path.display(), e));
+        Manifest::default()
+      }
+    },
+    Err(e) if e.kind() == ErrorKind::NotFound => Manifest::default(),
+    Err(e) => {
+      show_error(conn, format!("{}: {}", path.display(), e));
+      Manifest::default()
+    }
+  };
+  for target in ret.targets.iter() {
+    for file in target.files.iter() {
+      let file = match paths::to_path(file) {
+        Some(x) => x,
+        None => continue,
       };
-      let path = entry.path();
-      if !path.is_file() {
+      if !file.is_file() {
+        let msg = format!(
+          "{}: {}: no such file: {}",
+          path.display(),
+          target.name,
+          file.display()
+        );
+        show_error(conn, msg);
+      }
+    }
+  }
+  log::info!("{}: {} targets", path.display(), ret.targets.len());
+  ret
+}
+
+/// Returns a matcher for the files to ignore in the directory: those ignored
+/// by the `.gitignore` at its root, or by the given patterns.
+///
+/// The walk of the directory already skips the files ignored by any
+/// `.gitignore`, but we also check the files the client tells us were created
+/// or changed.
+fn get_excludes(
+  conn: &Client<'_>,
+  dir: &Path,
+  patterns: &[String],
+) -> Gitignore {
+  let mut builder = GitignoreBuilder::new(dir);
+  let gitignore = dir.join(".gitignore");
+  if gitignore.is_file() {
+    if let Some(e) = builder.add(gitignore) {
+      show_error(conn, e.to_string());
+    }
+  }
+  for pattern in patterns {
+    if let Err(e) = builder.add_line(None, pattern) {
+      show_error(conn, format!("{}: {}", manifest::FILE_NAME, e));
+    }
+  }
+  builder.build().unwrap_or_else(|e| {
+    show_error(conn, e.to_string());
+    Gitignore::empty()
+  })
+}
+
+/// Returns whether the path, in the folder of the excludes, is hidden (like the
+/// walk, we skip e.g. `.git`) or excluded.
+fn is_excluded(excludes: &Gitignore, path: &Path, is_dir: bool) -> bool {
+  let rel = match path.strip_prefix(excludes.path()) {
+    Ok(x) => x,
+    Err(_) => return false,
+  };
+  let hidden = rel.components().any(|c| match c {
+    Component::Normal(name) => name.to_string_lossy().starts_with('.'),
+    _ => false,
+  });
+  hidden
+    || excludes
+      .matched_path_or_any_parents(path, is_dir)
+      .is_ignore()
+}
+
+/// Returns a walk of the directory, which skips hidden files and dirs, like
+/// `.git`, and the files ignored by any `.gitignore`, even outside of a git
+/// repository.
+fn walk(dir: &Path) -> WalkBuilder {
+  let mut ret = WalkBuilder::new(dir);
+  ret.require_git(false);
+  ret
+}
+
+/// Returns the C0 files found by the walk, and their contents.
+fn get_files<'a>(
+  conn: &'a Client<'a>,
+  walk: Walk,
+) -> impl Iterator<Item = (Url, String)> + 'a {
+  walk.into_iter().filter_map(move |entry| {
+    let entry = match entry {
+      Ok(x) => x,
+      Err(e) => {
+        show_error(conn, e.to_string());
         return None;
       }
-      let ext = path.extension()?;
-      if ext != "c0" && ext != "h0" {
+    };
+    let path = entry.path();
+    if !path.is_file() {
+      return None;
+    }
+    let ext = path.extension()?;
+    if ext != "c0" && ext != "h0" {
+      return None;
+    }
+    let uri = match paths::from_path(path) {
+      Some(x) => x,
+      None => {
+        show_error(conn, format!("{}: not a valid URI", path.display()));
         return None;
       }
-      let uri = Url::from_file_path(path).expect("bad path");
-      match read_to_string(entry.path()) {
-        Ok(contents) => Some((uri, contents)),
-        Err(e) => {
-          show_error(conn, format!("{}: {}", path.display(), e));
-          None
-        }
+    };
+    match read_to_string(entry.path()) {
+      Ok(contents) => Some((uri, contents)),
+      Err(e) => {
+        show_error(conn, format!("{}: {}", path.display(), e));
+        None
       }
+    }
+  })
+}
+
+/// Asks the client to tell us when the C0 files or manifests change.
+fn register_watchers(conn: &Client<'_>) {
+  let patterns = [
+    "**/*.{c0,h0}".to_owned(),
+    format!("**/{}", manifest::FILE_NAME),
+  ];
+  let watchers = patterns
+    .into_iter()
+    .map(|pattern| FileSystemWatcher {
+      glob_pattern: pattern.into(),
+      kind: None,
     })
+    .collect();
+  let options = DidChangeWatchedFilesRegistrationOptions { watchers };
+  let registration = Registration {
+    id: WATCHERS.to_owned(),
+    method: DidChangeWatchedFiles::METHOD.to_owned(),
+    register_options: Some(
+      serde_json::to_value(options).expect("couldn't make JSON"),
+    ),
+  };
+  let params = RegistrationParams {
+    registrations: vec![registration],
+  };
+  let id = RequestId::from(WATCHERS.to_owned());
+  send(conn, mk_req::<RegisterCapability>(id, params));
 }
 
-fn send_all_diagnostics(conn: &Connection, db: &Db) {
-  for (uri, diagnostics) in db.all_diagnostics() {
-    let params = PublishDiagnosticsParams {
-      uri,
-      diagnostics: diagnostics.into_iter().map(CrateFrom::from).collect(),
-      version: None,
-    };
-    conn
-      .sender
-      .send(mk_notif::<PublishDiagnostics>(params))
-      .expect("couldn't send diagnostics");
+/// Asks the client to tell us when the settings change.
+fn register_configuration(conn: &Client<'_>) {
+  let registration = Registration {
+    id: CONFIGURATION.to_owned(),
+    method: DidChangeConfiguration::METHOD.to_owned(),
+    register_options: None,
+  };
+  let params = RegistrationParams {
+    registrations: vec![registration],
+  };
+  let id = RequestId::from(CONFIGURATION.to_owned());
+  send(conn, mk_req::<RegisterCapability>(id, params));
+}
+
+/// Asks the client for the settings.
+fn request_settings(conn: &Client<'_>) {
+  let params = ConfigurationParams {
+    items: vec![ConfigurationItem {
+      scope_uri: None,
+      section: Some(config::SECTION.to_owned()),
+    }],
+  };
+  let id = RequestId::from(SETTINGS.to_owned());
+  send(conn, mk_req::<WorkspaceConfiguration>(id, params));
+}
+
+fn report_progress(
+  conn: &Client<'_>,
+  token: &ProgressToken,
+  message: String,
+  percentage: Option<u32>,
+) {
+  let report = WorkDoneProgressReport {
+    cancellable: None,
+    message: Some(message),
+    percentage,
+  };
+  send_progress(conn, token.clone(), WorkDoneProgress::Report(report));
+}
+
+fn end_progress(conn: &Client<'_>, token: ProgressToken, message: String) {
+  let end = WorkDoneProgressEnd {
+    message: Some(message),
+  };
+  send_progress(conn, token, WorkDoneProgress::End(end));
+}
+
+fn send_progress(
+  conn: &Client<'_>,
+  token: ProgressToken,
+  value: WorkDoneProgress,
+) {
+  let params = ProgressParams {
+    token,
+    value: ProgressParamsValue::WorkDone(value),
+  };
+  send(conn, mk_notif::<Progress>(params));
+}
+
+fn send_diagnostics(
+  conn: &Client<'_>,
+  uri: Url,
+  diagnostics: Vec<lsp_types::Diagnostic>,
+  version: Option<i32>,
+) {
+  let params = PublishDiagnosticsParams {
+    uri,
+    diagnostics,
+    version,
+  };
+  send(conn, mk_notif::<PublishDiagnostics>(params));
+}
+
+/// Runs `f`, returning the message it panicked with, if it did.
+///
+/// What we were in the middle of when we panicked may be left a bit off, but
+/// carrying on beats the alternative: the client losing every feature until it
+/// starts us again.
+fn catch_panic<T, F>(f: F) -> Result<T, String>
+where
+  F: FnOnce() -> T,
+{
+  catch_unwind(AssertUnwindSafe(f)).map_err(|e| {
+    if let Some(s) = e.downcast_ref::<&str>() {
+      (*s).to_owned()
+    } else if let Some(s) = e.downcast_ref::<String>() {
+      s.clone()
+    } else {
+      "unknown panic".to_owned()
+    }
+  })
+}
+
+/// Sends the message, unless the client is gone, e.g. because it disconnected.
+fn send(conn: &Client<'_>, msg: Message) {
+  if conn.gone.load(Ordering::Relaxed) {
+    return;
+  }
+  if conn.conn.sender.send(msg).is_err() {
+    log::error!("couldn't send a message, so the client is gone");
+    conn.gone.store(true, Ordering::Relaxed);
   }
 }
 
+fn mk_req<R>(id: RequestId, val: R::Params) -> Message
+where
+  R: lsp_types::request::Request,
+{
+  Message::Request(lsp_server::Request {
+    id,
+    method: R::METHOD.to_owned(),
+    params: serde_json::to_value(val).expect("couldn't make JSON"),
+  })
+}
+
 fn mk_notif<N>(val: N::Params) -> Message
 where
   N: lsp_types::notification::Notification,
@@ -170,12 +1290,36 @@ where
   })
 }
 
-fn show_error(conn: &Connection, message: String) {
-  conn
-    .sender
-    .send(mk_notif::<ShowMessage>(ShowMessageParams {
-      typ: MessageType::ERROR,
-      message,
-    }))
-    .expect("couldn't show error")
+fn show_error(conn: &Client<'_>, message: String) {
+  let msg = mk_notif::<ShowMessage>(ShowMessageParams {
+    typ: MessageType::ERROR,
+    message,
+  });
+  send(conn, msg);
+}
+
+#[cfg(test)]
+mod tests {
+  use ignore::gitignore::Gitignore;
+  use std::fs;
+
+  #[test]
+  fn skip_hidden() {
+    let dir = std::env::temp_dir().join(format!("c0ls-{}", std::process::id()));
+    fs::create_dir_all(dir.join(".git")).unwrap();
+    fs::write(dir.join("a.c0"), "").unwrap();
+    fs::write(dir.join(".git").join("b.c0"), "").unwrap();
+    let files: Vec<_> = super::walk(&dir)
+      .build()
+      .map(|entry| entry.unwrap().into_path())
+      .filter(|path| path.is_file())
+      .collect();
+    let excludes = Gitignore::new(dir.join(".gitignore")).0;
+    let hidden = super::is_excluded(&excludes, &dir.join(".git/b.c0"), false);
+    let shown = super::is_excluded(&excludes, &dir.join("a.c0"), false);
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(files, [dir.join("a.c0")]);
+    assert!(hidden);
+    assert!(!shown);
+  }
 }
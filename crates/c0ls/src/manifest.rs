@@ -0,0 +1,117 @@
+//! The manifest of a workspace folder, `c0ls.toml` at its root, which lists the
+//! targets in the folder.
+//!
+//! A target is like a `cc0` command line: a list of files, each of which sees
+//! the items of the files before it.
+//!
+//! ```toml
+//! [[target]]
+//! name = "queue"
+//! files = ["lib.c0", "queue.c0", "main.c0"]
+//! ```
+//!
+//! The paths of the files are relative to the folder, and may go outside it with
+//! `..`.
+//!
+//! The manifest may also list files to ignore, in addition to those ignored by
+//! `.gitignore` files, in the same syntax:
+//!
+//! ```toml
+//! exclude = ["target/", "old-submissions/"]
+//! ```
+
+use analysis::Target;
+use lsp_types::Url;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// The name of the manifest file.
+pub(crate) const FILE_NAME: &str = "c0ls.toml";
+
+/// A parsed manifest.
+#[derive(Debug, Default)]
+pub(crate) struct Manifest {
+  pub(crate) targets: Vec<Target>,
+  pub(crate) exclude: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawManifest {
+  #[serde(default)]
+  exclude: Vec<String>,
+  #[serde(default)]
+  target: Vec<ManifestTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ManifestTarget {
+  name: String,
+  files: Vec<String>,
+}
+
+/// Returns the URI of the manifest of the folder.
+pub(crate) fn uri(folder: &Url) -> Option<Url> {
+  paths::join(folder, FILE_NAME).ok()
+}
+
+/// Parses the contents of the manifest of the folder.
+pub(crate) fn get(folder: &Path, contents: &str) -> Result<Manifest, String> {
+  let raw: RawManifest = toml::from_str(contents).map_err(|e| e.to_string())?;
+  let mut names = HashSet::new();
+  let targets = raw
+    .target
+    .into_iter()
+    .map(|target| {
+      if !names.insert(target.name.clone()) {
+        return Err(format!("duplicate target: {}", target.name));
+      }
+      let files = target
+        .files
+        .iter()
+        .map(|file| {
+          // the files we load from disk have no `.` or `..` in their paths.
+          paths::from_path(&paths::normalize(&folder.join(file)))
+            .ok_or_else(|| format!("{}: bad path: {}", target.name, file))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+      Ok(Target {
+        name: target.name,
+        files,
+      })
+    })
+    .collect::<Result<Vec<_>, _>>()?;
+  Ok(Manifest {
+    targets,
+    exclude: raw.exclude,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  #[test]
+  fn relative_files() {
+    let contents = r#"
+[[target]]
+name = "t"
+files = ["./a.c0", "src/../b.c0", "../lib/c.c0", "d/./e.c0"]
+"#;
+    let manifest = super::get(Path::new("/ws/proj"), contents).unwrap();
+    let files: Vec<_> = manifest.targets[0]
+      .files
+      .iter()
+      .map(|uri| paths::to_path(uri).unwrap())
+      .collect();
+    let want = [
+      "/ws/proj/a.c0",
+      "/ws/proj/b.c0",
+      "/ws/lib/c.c0",
+      "/ws/proj/d/e.c0",
+    ];
+    assert_eq!(files, want.map(Path::new));
+  }
+}
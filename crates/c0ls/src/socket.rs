@@ -0,0 +1,94 @@
+//! Serving many clients over TCP, each with a server of its own.
+//!
+//! `lsp_server::Connection::listen` accepts only one client, so we accept them
+//! ourselves and make the connections like it would.
+
+use crossbeam_channel::{bounded, Receiver, Sender};
+use lsp_server::{Connection, Message};
+use lsp_types::notification::{Exit, Notification as _};
+use std::io::{self, BufReader};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::thread;
+
+/// Accepts clients at the address until we can't, running `serve` on its own
+/// thread for each of them.
+pub(crate) fn listen<F>(addr: &str, serve: F) -> io::Result<()>
+where
+  F: Fn(Connection) + Clone + Send + 'static,
+{
+  let listener = TcpListener::bind(addr)?;
+  log::info!("listening on {}", listener.local_addr()?);
+  for stream in listener.incoming() {
+    let stream = match stream {
+      Ok(x) => x,
+      Err(e) => {
+        log::error!("couldn't accept a client: {}", e);
+        continue;
+      }
+    };
+    let serve = serve.clone();
+    thread::spawn(move || {
+      let peer = stream
+        .peer_addr()
+        .map_or_else(|_| "a client".to_owned(), |x| x.to_string());
+      log::info!("serving {}", peer);
+      if let Err(e) = serve_one(stream, serve) {
+        log::error!("couldn't serve {}: {}", peer, e);
+      }
+      log::info!("done serving {}", peer);
+    });
+  }
+  Ok(())
+}
+
+fn serve_one<F>(stream: TcpStream, serve: F) -> io::Result<()>
+where
+  F: FnOnce(Connection),
+{
+  let (receiver, reader) = reader(stream.try_clone()?);
+  let (sender, writer) = writer(stream.try_clone()?);
+  serve(Connection { sender, receiver });
+  // the server is done, but the client may not be, so the reader may be
+  // waiting for a message that never comes.
+  let _ = stream.shutdown(Shutdown::Read);
+  join(reader)?;
+  join(writer)
+}
+
+fn reader(
+  stream: TcpStream,
+) -> (Receiver<Message>, thread::JoinHandle<io::Result<()>>) {
+  let (sender, receiver) = bounded::<Message>(0);
+  let handle = thread::spawn(move || {
+    let mut stream = BufReader::new(stream);
+    while let Some(msg) = Message::read(&mut stream)? {
+      let is_exit =
+        matches!(&msg, Message::Notification(n) if n.method == Exit::METHOD);
+      if sender.send(msg).is_err() || is_exit {
+        break;
+      }
+    }
+    Ok(())
+  });
+  (receiver, handle)
+}
+
+fn writer(
+  mut stream: TcpStream,
+) -> (Sender<Message>, thread::JoinHandle<io::Result<()>>) {
+  let (sender, receiver) = bounded::<Message>(0);
+  let handle = thread::spawn(move || {
+    for msg in receiver {
+      msg.write(&mut stream)?;
+    }
+    Ok(())
+  });
+  (sender, handle)
+}
+
+fn join(handle: thread::JoinHandle<io::Result<()>>) -> io::Result<()> {
+  match handle.join() {
+    Ok(x) => x,
+    Err(_) => Err(io::Error::new(io::ErrorKind::Other, "thread panicked")),
+  }
+}
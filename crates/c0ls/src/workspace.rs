@@ -0,0 +1,182 @@
+//! The files of the workspace, split up by workspace folder.
+//!
+//! Each folder gets its own `Db`, so e.g. a `#use` cycle in one folder doesn't
+//! stop us from analyzing the others. Files not in any folder, like untitled
+//! buffers, or any file when the client opened no folder at all, go in one more
+//! `Db` of their own.
+//!
+//! Every `Db` gets its config from the same settings, but relative include dirs
+//! are relative to the folder. The files in the include dirs outside of a folder
+//! go in that folder's `Db`, and so do the updates to them. If folders share
+//! such a dir, the first of them gets its files.
+
+use crate::config::Settings;
+use analysis::{Db, Target, Update};
+use lsp_types::Url;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+pub(crate) struct Workspace {
+  folders: Vec<Folder>,
+  /// the files not in any folder.
+  loose: Db,
+}
+
+#[derive(Debug)]
+struct Folder {
+  uri: Url,
+  db: Db,
+  /// the include dirs outside of the folder.
+  includes: Vec<PathBuf>,
+}
+
+impl Workspace {
+  /// Returns a workspace with the given folders and settings, and no files.
+  pub(crate) fn new(folders: Vec<Url>, settings: &Settings) -> Self {
+    let mut ret = Self {
+      folders: folders
+        .into_iter()
+        .map(|uri| Folder {
+          uri,
+          db: Db::new(std::iter::empty()),
+          includes: Vec::new(),
+        })
+        .collect(),
+      loose: Db::new(std::iter::empty()),
+    };
+    ret.set_settings(settings);
+    ret
+  }
+
+  /// Sets the config of every `Db`, and the include dirs of every folder, from
+  /// the settings.
+  pub(crate) fn set_settings(&mut self, settings: &Settings) {
+    for folder in self.folders.iter_mut() {
+      let path = paths::to_path(&folder.uri);
+      folder.db.set_config(settings.config(path.as_deref()));
+      folder.includes = match path {
+        Some(path) => settings
+          .include_paths(Some(&path))
+          .filter(|inc| !inc.starts_with(&path))
+          .collect(),
+        None => Vec::new(),
+      };
+    }
+    self.loose.set_config(settings.config(None));
+  }
+
+  pub(crate) fn folders(&self) -> impl Iterator<Item = &Url> + '_ {
+    self.folders.iter().map(|f| &f.uri)
+  }
+
+  /// Returns the folder the file is in. If the folders are nested, this is the
+  /// innermost one.
+  pub(crate) fn folder(&self, uri: &Url) -> Option<&Url> {
+    self.containing_idx(uri).map(|idx| &self.folders[idx].uri)
+  }
+
+  /// Returns the include dirs outside of the folder.
+  pub(crate) fn includes(&self, folder: &Url) -> &[PathBuf] {
+    match self.folders.iter().find(|f| f.uri == *folder) {
+      Some(f) => &f.includes,
+      None => &[],
+    }
+  }
+
+  /// Sets the targets of the folder's `Db`.
+  pub(crate) fn set_targets(&mut self, folder: &Url, targets: Vec<Target>) {
+    if let Some(f) = self.folders.iter_mut().find(|f| f.uri == *folder) {
+      f.db.set_targets(targets);
+    }
+  }
+
+  /// Returns whether the file goes in the `Db` for the files not in any folder,
+  /// i.e. it is neither in a folder nor in the include dirs of one.
+  pub(crate) fn is_loose(&self, uri: &Url) -> bool {
+    self.folder_idx(uri).is_none()
+  }
+
+  /// Returns the `Db` that has, or would have, the file.
+  pub(crate) fn get(&self, uri: &Url) -> &Db {
+    match self.folder_idx(uri) {
+      Some(idx) => &self.folders[idx].db,
+      None => &self.loose,
+    }
+  }
+
+  pub(crate) fn get_mut(&mut self, uri: &Url) -> &mut Db {
+    match self.folder_idx(uri) {
+      Some(idx) => &mut self.folders[idx].db,
+      None => &mut self.loose,
+    }
+  }
+
+  /// Returns all the `Db`s.
+  pub(crate) fn dbs(&self) -> impl Iterator<Item = &Db> + '_ {
+    self
+      .folders
+      .iter()
+      .map(|f| &f.db)
+      .chain(std::iter::once(&self.loose))
+  }
+
+  /// Sends each update to the `Db` that has the file.
+  pub(crate) fn update_files<I>(&mut self, updates: I)
+  where
+    I: IntoIterator<Item = Update>,
+  {
+    let mut by_db: Vec<Vec<Update>> = Vec::new();
+    by_db.resize_with(self.folders.len() + 1, Vec::new);
+    for update in updates {
+      let uri = match &update {
+        Update::Create(uri, _) | Update::Delete(uri) => uri,
+      };
+      let idx = self.folder_idx(uri).unwrap_or(self.folders.len());
+      by_db[idx].push(update);
+    }
+    let dbs = self
+      .folders
+      .iter_mut()
+      .map(|f| &mut f.db)
+      .chain(std::iter::once(&mut self.loose));
+    for (db, updates) in dbs.zip(by_db) {
+      if !updates.is_empty() {
+        db.update_files(updates);
+      }
+    }
+  }
+
+  /// Returns the folder whose `Db` has, or would have, the file.
+  fn folder_idx(&self, uri: &Url) -> Option<usize> {
+    self.containing_idx(uri).or_else(|| {
+      let path = paths::to_path(uri)?;
+      self
+        .folders
+        .iter()
+        .position(|f| in_includes(&f.includes, &path))
+    })
+  }
+
+  fn containing_idx(&self, uri: &Url) -> Option<usize> {
+    self
+      .folders
+      .iter()
+      .enumerate()
+      .filter(|(_, f)| contains(&f.uri, uri))
+      .max_by_key(|(_, f)| f.uri.as_str().len())
+      .map(|(idx, _)| idx)
+  }
+}
+
+fn in_includes(includes: &[PathBuf], path: &Path) -> bool {
+  includes.iter().any(|inc| path.starts_with(inc))
+}
+
+/// Returns whether the file is in the folder.
+fn contains(folder: &Url, uri: &Url) -> bool {
+  let folder = folder.as_str().trim_end_matches('/');
+  uri
+    .as_str()
+    .strip_prefix(folder)
+    .is_some_and(|rest| rest.starts_with('/'))
+}
@@ -1,4 +1,4 @@
-use lsp_server::{Notification, Request, RequestId, Response};
+use lsp_server::{ErrorCode, Notification, Request, RequestId, Response};
 
 #[derive(Debug)]
 pub(crate) struct Req(Request);
@@ -8,28 +8,45 @@ impl Req {
     Self(req)
   }
 
+  /// Handles the request if it has the method of `R`. If the params are bad,
+  /// or the result can't be serialized, the response is an error.
   pub(crate) fn handle<R, F>(self, f: F) -> Result<Self, Response>
   where
     R: lsp_types::request::Request,
     F: FnOnce(RequestId, R::Params) -> R::Result,
   {
-    match self.0.clone().extract::<R::Params>(R::METHOD) {
-      Ok((id, params)) => {
-        let result = f(id.clone(), params);
-        let val = serde_json::to_value(&result).expect("couldn't make JSON");
-        Err(Response {
-          id,
-          result: Some(val),
-          error: None,
-        })
+    if self.0.method != R::METHOD {
+      return Ok(self);
+    }
+    let Request { id, params, .. } = self.0;
+    let params = match serde_json::from_value::<R::Params>(params) {
+      Ok(x) => x,
+      Err(e) => {
+        let msg = format!("bad params for {}: {}", R::METHOD, e);
+        return Err(error(id, ErrorCode::InvalidParams, msg));
+      }
+    };
+    let result = f(id.clone(), params);
+    match serde_json::to_value(&result) {
+      Ok(val) => Err(Response {
+        id,
+        result: Some(val),
+        error: None,
+      }),
+      Err(e) => {
+        let msg = format!("couldn't make JSON: {}", e);
+        Err(error(id, ErrorCode::InternalError, msg))
       }
-      Err(_) => Ok(self),
     }
   }
 
   pub(crate) fn method(&self) -> &str {
     self.0.method.as_str()
   }
+
+  pub(crate) fn into_inner(self) -> Request {
+    self.0
+  }
 }
 
 #[derive(Debug)]
@@ -43,21 +60,37 @@ impl Notif {
     Self(notif)
   }
 
+  /// Handles the notification if it has the method of `N`. If the params are
+  /// bad, we log it and do nothing else, since there's no one to tell.
   pub(crate) fn handle<N, F>(self, f: F) -> Result<Self, Handled>
   where
     N: lsp_types::notification::Notification,
     F: FnOnce(N::Params),
   {
-    match self.0.clone().extract::<N::Params>(N::METHOD) {
-      Ok(params) => {
-        f(params);
-        Err(Handled)
-      }
-      Err(_) => Ok(self),
+    if self.0.method != N::METHOD {
+      return Ok(self);
     }
+    match serde_json::from_value::<N::Params>(self.0.params) {
+      Ok(params) => f(params),
+      Err(e) => log::error!("bad params for {}: {}", N::METHOD, e),
+    }
+    Err(Handled)
   }
 
   pub(crate) fn method(&self) -> &str {
     self.0.method.as_str()
   }
+
+  pub(crate) fn into_inner(self) -> Notification {
+    self.0
+  }
+}
+
+/// Returns an error response to the request.
+pub(crate) fn error(
+  id: RequestId,
+  code: ErrorCode,
+  message: String,
+) -> Response {
+  Response::new_err(id, code as i32, message)
 }
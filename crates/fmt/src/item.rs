@@ -1,7 +1,6 @@
 use crate::stmt::get_block;
 use crate::ty::get as get_ty;
 use crate::util::Cx;
-use crate::util::INDENT;
 use syntax::ast::{Field, FnTail, Item};
 
 pub(crate) fn get(cx: &mut Cx, item: Item) -> Option<()> {
@@ -88,7 +87,7 @@ pub(crate) fn get(cx: &mut Cx, item: Item) -> Option<()> {
 }
 
 fn get_field(cx: &mut Cx, field: Field) -> Option<()> {
-  cx.push(INDENT);
+  cx.indent();
   get_ty(cx, field.ty()?)?;
   cx.push(" ");
   cx.push(field.ident()?.text());
@@ -16,3 +16,21 @@ mod ty;
 mod util;
 
 pub use root::get;
+
+/// How to format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Style {
+  /// The number of spaces to indent by, if not using tabs.
+  pub indent_width: usize,
+  /// Whether to indent with tabs instead of spaces.
+  pub tabs: bool,
+}
+
+impl Default for Style {
+  fn default() -> Self {
+    Self {
+      indent_width: 2,
+      tabs: false,
+    }
+  }
+}
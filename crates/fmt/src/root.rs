@@ -1,5 +1,6 @@
 use crate::item::get as get_item;
 use crate::util::Cx;
+use crate::Style;
 use syntax::ast::{Item, Root};
 
 /// Requires that `root` is lexically and syntactically valid. Ensures this
@@ -10,8 +11,8 @@ use syntax::ast::{Item, Root};
 ///
 /// If an invalid `root` is passed, `None` may be returned. Or, `Some(s)` where
 /// `s` has different semantics from `root` may also be returned.
-pub fn get(root: Root) -> Option<String> {
-  let mut cx = Cx::default();
+pub fn get(root: Root, style: &Style) -> Option<String> {
+  let mut cx = Cx::new(style);
   let mut prev_pragma = true;
   let mut items = root.items();
   if let Some(item) = items.next() {
@@ -1,6 +1,6 @@
 use crate::expr::get as get_expr;
 use crate::simp::get as get_simp;
-use crate::util::{Cx, INDENT};
+use crate::util::Cx;
 use syntax::ast::{BlockStmt, Stmt};
 
 pub(crate) fn get_block(cx: &mut Cx, stmt: BlockStmt) -> Option<()> {
@@ -14,13 +14,13 @@ where
   cx.push("{\n");
   for s in stmts {
     for _ in 0..(level + 1) {
-      cx.push(INDENT);
+      cx.indent();
     }
     get_one(cx, level + 1, s)?;
     cx.push("\n");
   }
   for _ in 0..level {
-    cx.push(INDENT);
+    cx.indent();
   }
   cx.push("}");
   Some(())
@@ -1,11 +1,13 @@
+use crate::Style;
 use syntax::ast::Root;
 
 fn check(inp: &str, out: &str) {
   let inp_root = get_root(inp);
   let out_root = get_root(out);
-  assert_eq!(crate::get(inp_root).unwrap(), out);
+  let style = Style::default();
+  assert_eq!(crate::get(inp_root, &style).unwrap(), out);
   // idempotent
-  assert_eq!(crate::get(out_root).unwrap(), out);
+  assert_eq!(crate::get(out_root, &style).unwrap(), out);
 }
 
 fn get_root(s: &str) -> Root {
@@ -31,3 +33,24 @@ fn if_return() {
     include_str!("data/if_return.out.c0"),
   );
 }
+
+#[test]
+fn style() {
+  let root = get_root("int f(){if(true){return 1;}return 2;}");
+  let style = Style {
+    indent_width: 4,
+    tabs: false,
+  };
+  assert_eq!(
+    crate::get(root.clone(), &style).unwrap(),
+    "int f() {\n    if (true) {\n        return 1;\n    }\n    return 2;\n}\n"
+  );
+  let style = Style {
+    indent_width: 4,
+    tabs: true,
+  };
+  assert_eq!(
+    crate::get(root, &style).unwrap(),
+    "int f() {\n\tif (true) {\n\t\treturn 1;\n\t}\n\treturn 2;\n}\n"
+  );
+}
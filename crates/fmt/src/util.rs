@@ -1,15 +1,31 @@
-pub(crate) const INDENT: &str = "  ";
+use crate::Style;
 
-#[derive(Default)]
 pub(crate) struct Cx {
   buf: String,
+  indent: String,
 }
 
 impl Cx {
+  pub(crate) fn new(style: &Style) -> Self {
+    let indent = if style.tabs {
+      "\t".to_owned()
+    } else {
+      " ".repeat(style.indent_width)
+    };
+    Self {
+      buf: String::new(),
+      indent,
+    }
+  }
+
   pub(crate) fn push(&mut self, s: &str) {
     self.buf.push_str(s);
   }
 
+  pub(crate) fn indent(&mut self) {
+    self.buf.push_str(&self.indent);
+  }
+
   pub(crate) fn finish(self) -> String {
     self.buf
   }
@@ -0,0 +1,282 @@
+//! Generating random cases from a seed.
+
+use analysis::{Config, Level, Lints, Position, Range, Style, Verbosity};
+
+/// The files we start from: the analysis test data, and a few more.
+const CORPUS: [&str; 12] = [
+  include_str!("../../analysis/src/tests/data/arrow.c0"),
+  include_str!("../../analysis/src/tests/data/bad_op.c0"),
+  include_str!("../../analysis/src/tests/data/cond.c0"),
+  include_str!("../../analysis/src/tests/data/def.c0"),
+  include_str!("../../analysis/src/tests/data/hover_ty.c0"),
+  include_str!("../../analysis/src/tests/data/lints.c0"),
+  include_str!("../../analysis/src/tests/data/not_in_loop.c0"),
+  include_str!("../../analysis/src/tests/data/semantic_tokens.c0"),
+  include_str!("../../analysis/src/tests/data/use_lib.c0"),
+  include_str!("../../analysis/src/tests/data/transitive/1.h0"),
+  include_str!("../../analysis/src/tests/data/transitive/3.c0"),
+  "#use \"a.c0\"\n#use \"b.h0\"\n#use <conio>\nint x = 3;\n",
+];
+
+/// Bits of text to insert.
+const TOKENS: [&str; 40] = [
+  "int",
+  "bool",
+  "char",
+  "string",
+  "void",
+  "struct",
+  "typedef",
+  "if",
+  "else",
+  "while",
+  "for",
+  "return",
+  "break",
+  "continue",
+  "alloc",
+  "alloc_array",
+  "NULL",
+  "true",
+  "x",
+  "f",
+  "s",
+  "(",
+  ")",
+  "{",
+  "}",
+  "[",
+  "]",
+  ";",
+  ",",
+  "*",
+  "->",
+  "=",
+  "==",
+  "+=",
+  "\n",
+  "//",
+  "/*",
+  "\"",
+  "'",
+  "ü",
+];
+
+/// Bits of text to insert at the start of a line.
+const LINES: [&str; 6] = [
+  "#use \"a.c0\"\n",
+  "#use \"../b.h0\"\n",
+  "#use <conio>\n",
+  "#use <nope>\n",
+  "/* doc */\nint f(int x);\n",
+  "struct s { int a; struct s* b; };\n",
+];
+
+/// The names of the files, relative to the root.
+pub(crate) const NAMES: [&str; 4] = ["a.c0", "b.h0", "c.c0", "d/e.c0"];
+
+/// A random number generator (xorshift64*), so a case depends only on its
+/// seed.
+#[derive(Debug)]
+pub(crate) struct Rng(u64);
+
+impl Rng {
+  pub(crate) fn new(seed: u64) -> Self {
+    // the state must not be zero.
+    Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+  }
+
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 >> 12;
+    self.0 ^= self.0 << 25;
+    self.0 ^= self.0 >> 27;
+    self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+  }
+
+  /// Returns a number in `0..n`, which must be positive.
+  pub(crate) fn below(&mut self, n: usize) -> usize {
+    (self.next() % n as u64) as usize
+  }
+
+  pub(crate) fn bool(&mut self) -> bool {
+    self.below(2) == 0
+  }
+
+  pub(crate) fn pick<'a, T>(&mut self, xs: &'a [T]) -> &'a T {
+    &xs[self.below(xs.len())]
+  }
+}
+
+/// A case: some files, and things to do with them.
+#[derive(Debug)]
+pub(crate) struct Case {
+  pub(crate) files: Vec<(&'static str, String)>,
+  pub(crate) ops: Vec<Op>,
+}
+
+/// A thing to do with the files. The file is an index into `NAMES`.
+#[derive(Debug)]
+pub(crate) enum Op {
+  Edit(usize, Option<Range>, String),
+  Create(usize, String),
+  Delete(usize),
+  Open(usize, String),
+  Close(usize),
+  Save(usize),
+  SetTargets(Vec<Vec<usize>>),
+  SetConfig(Config),
+  Diagnostics,
+  FileDiagnostics(usize),
+  Hover(usize, Position),
+  GoToDef(usize, Position),
+  Completions(usize, Position),
+  SemanticTokens(usize, Option<Range>),
+  FoldingRanges(usize),
+  SelectionRanges(usize, Vec<Position>),
+  CallHierarchy(usize, Position),
+  Format(usize),
+  Inspect(usize),
+  MemoryUsage,
+}
+
+pub(crate) fn case(rng: &mut Rng) -> Case {
+  let mut files = Vec::new();
+  for &name in NAMES.iter() {
+    if rng.below(4) != 0 {
+      files.push((name, text(rng)));
+    }
+  }
+  let ops = (0..rng.below(30)).map(|_| op(rng)).collect();
+  Case { files, ops }
+}
+
+/// Returns some C0-ish text: a file from the corpus, mutated a bit.
+fn text(rng: &mut Rng) -> String {
+  let mut ret = rng.pick(&CORPUS).to_string();
+  for _ in 0..rng.below(8) {
+    let at = char_boundary(&ret, rng.below(ret.len() + 1));
+    match rng.below(4) {
+      0 => {
+        let token = *rng.pick(&TOKENS);
+        ret.insert_str(at, token);
+      }
+      1 => {
+        let line_start = ret[..at].rfind('\n').map_or(0, |x| x + 1);
+        let line = *rng.pick(&LINES);
+        ret.insert_str(line_start, line);
+      }
+      2 => {
+        let end = char_boundary(&ret, at + rng.below(20));
+        ret.replace_range(at..end, "");
+      }
+      _ => {
+        let other = rng.pick(&CORPUS);
+        let start = char_boundary(other, rng.below(other.len() + 1));
+        let end = char_boundary(other, start + rng.below(80));
+        ret.insert_str(at, &other[start..end]);
+      }
+    }
+  }
+  ret
+}
+
+fn op(rng: &mut Rng) -> Op {
+  let file = rng.below(NAMES.len());
+  match rng.below(19) {
+    0..=3 => {
+      let range = (rng.below(8) != 0).then(|| range(rng));
+      let text = if range.is_some() {
+        (0..rng.below(3)).map(|_| *rng.pick(&TOKENS)).collect()
+      } else {
+        text(rng)
+      };
+      Op::Edit(file, range, text)
+    }
+    4 => Op::Create(file, text(rng)),
+    5 => Op::Delete(file),
+    6 => Op::Open(file, text(rng)),
+    7 => Op::Close(file),
+    8 => Op::Save(file),
+    9 => {
+      let targets = (0..rng.below(3))
+        .map(|_| (0..rng.below(4)).map(|_| rng.below(NAMES.len())).collect())
+        .collect();
+      Op::SetTargets(targets)
+    }
+    10 => Op::SetConfig(config(rng)),
+    11 => Op::Diagnostics,
+    12 => Op::FileDiagnostics(file),
+    13 => Op::Hover(file, position(rng)),
+    14 => Op::GoToDef(file, position(rng)),
+    15 => Op::Completions(file, position(rng)),
+    16 => match rng.below(4) {
+      0 => Op::SemanticTokens(file, None),
+      1 => Op::SemanticTokens(file, Some(range(rng))),
+      2 => Op::FoldingRanges(file),
+      _ => Op::SelectionRanges(
+        file,
+        (0..rng.below(3)).map(|_| position(rng)).collect(),
+      ),
+    },
+    17 => Op::CallHierarchy(file, position(rng)),
+    _ => match rng.below(3) {
+      0 => Op::Format(file),
+      1 => Op::Inspect(file),
+      _ => Op::MemoryUsage,
+    },
+  }
+}
+
+fn config(rng: &mut Rng) -> Config {
+  Config {
+    level: if rng.bool() { Level::C0 } else { Level::C1 },
+    include_dirs: Vec::new(),
+    lints: Lints {
+      suspicious: rng.bool(),
+      style: rng.bool(),
+    },
+    format: Style {
+      indent_width: rng.below(9),
+      tabs: rng.bool(),
+    },
+    verbosity: if rng.bool() {
+      Verbosity::Quiet
+    } else {
+      Verbosity::Normal
+    },
+  }
+}
+
+/// Returns a position, usually but not always in the file.
+fn position(rng: &mut Rng) -> Position {
+  Position {
+    line: rng.below(40) as u32,
+    character: {
+      let max = if rng.below(8) == 0 { 1000 } else { 40 };
+      rng.below(max) as u32
+    },
+  }
+}
+
+/// Returns a range, which may be backwards.
+fn range(rng: &mut Rng) -> Range {
+  let start = position(rng);
+  let end = if rng.below(8) == 0 {
+    position(rng)
+  } else {
+    Position {
+      line: start.line + rng.below(2) as u32,
+      character: rng.below(40) as u32,
+    }
+  };
+  Range { start, end }
+}
+
+/// Returns the largest char boundary in `s` at most `idx`.
+fn char_boundary(s: &str, idx: usize) -> usize {
+  let mut idx = idx.min(s.len());
+  while !s.is_char_boundary(idx) {
+    idx -= 1;
+  }
+  idx
+}
@@ -0,0 +1,142 @@
+//! Fuzzing the `Db` entry points with random files, edits, and queries, none of
+//! which may panic or hang.
+//!
+//! Run with `cargo xtask fuzz [CASES] [FIRST_SEED]`. Each case depends only on
+//! its seed, so a failing case can be run again alone with `fuzz 1 SEED`.
+
+#![deny(missing_debug_implementations)]
+#![deny(rust_2018_idioms)]
+
+mod gen;
+
+use analysis::{Db, Edit, Target, Update};
+use gen::{Case, Op, Rng, NAMES};
+use std::sync::mpsc;
+use std::time::Duration;
+use uri_db::Uri;
+
+/// How long a case may take before we say it hangs.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+fn main() {
+  let mut args = std::env::args().skip(1);
+  let cases: u64 = match args.next() {
+    Some(x) => x.parse().expect("CASES must be a number"),
+    None => 10_000,
+  };
+  let first: u64 = match args.next() {
+    Some(x) => x.parse().expect("FIRST_SEED must be a number"),
+    None => 0,
+  };
+  for seed in first..first + cases {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+      // a panic drops the sender without sending.
+      run(&gen::case(&mut Rng::new(seed)));
+      let _ = sender.send(());
+    });
+    match receiver.recv_timeout(TIMEOUT) {
+      Ok(()) => {}
+      Err(e) => {
+        let what = match e {
+          mpsc::RecvTimeoutError::Timeout => "hung",
+          mpsc::RecvTimeoutError::Disconnected => "panicked",
+        };
+        println!("seed {seed} {what}:\n{:#?}", gen::case(&mut Rng::new(seed)));
+        std::process::exit(1);
+      }
+    }
+    if seed % 1000 == 999 {
+      println!("{} cases ok", seed + 1 - first);
+    }
+  }
+  println!("all {cases} cases ok");
+}
+
+fn uri(file: usize) -> Uri {
+  Uri::from_file_path(format!("/fuzz/{}", NAMES[file])).unwrap()
+}
+
+/// Runs the case, panicking if the `Db` does.
+fn run(case: &Case) {
+  let files = case.files.iter().map(|(name, contents)| {
+    let file = NAMES.iter().position(|x| x == name).unwrap();
+    (uri(file), contents.clone())
+  });
+  let mut db = Db::new(files);
+  for op in case.ops.iter() {
+    match op {
+      Op::Edit(file, range, text) => {
+        let edit = Edit {
+          text: text.clone(),
+          range: *range,
+        };
+        db.edit_file(&uri(*file), [edit]);
+      }
+      Op::Create(file, text) => {
+        db.update_files([Update::Create(uri(*file), text.clone())])
+      }
+      Op::Delete(file) => db.update_files([Update::Delete(uri(*file))]),
+      Op::Open(file, text) => db.open_file(uri(*file), text.clone()),
+      Op::Close(file) => db.close_file(&uri(*file)),
+      Op::Save(file) => db.save_file(&uri(*file)),
+      Op::SetTargets(targets) => {
+        db.set_targets(targets.iter().enumerate().map(|(idx, files)| Target {
+          name: idx.to_string(),
+          files: files.iter().map(|&x| uri(x)).collect(),
+        }))
+      }
+      Op::SetConfig(config) => db.set_config(config.clone()),
+      Op::Diagnostics => {
+        db.all_diagnostics();
+      }
+      Op::FileDiagnostics(file) => {
+        db.file_diagnostics(&uri(*file));
+      }
+      Op::Hover(file, pos) => {
+        db.hover(&uri(*file), *pos);
+      }
+      Op::GoToDef(file, pos) => {
+        db.go_to_def(&uri(*file), *pos);
+      }
+      Op::Completions(file, pos) => {
+        db.completions(&uri(*file), *pos);
+      }
+      Op::SemanticTokens(file, range) => {
+        db.semantic_tokens(&uri(*file), *range);
+      }
+      Op::FoldingRanges(file) => {
+        db.folding_ranges(&uri(*file));
+      }
+      Op::SelectionRanges(file, positions) => {
+        db.selection_ranges(&uri(*file), positions);
+      }
+      Op::CallHierarchy(file, pos) => {
+        if let Some(item) = db.fn_item(&uri(*file), *pos) {
+          db.incoming_calls(&item);
+          db.outgoing_calls(&item);
+        }
+        db.calls();
+      }
+      Op::Format(file) => {
+        db.format(&uri(*file));
+      }
+      Op::Inspect(file) => {
+        db.syntax_tree(&uri(*file));
+        db.tokens(&uri(*file));
+        db.hir(&uri(*file));
+      }
+      Op::MemoryUsage => {
+        db.memory_usage();
+      }
+    }
+  }
+  db.all_diagnostics();
+}
+
+#[test]
+fn some_cases() {
+  for seed in 0..300 {
+    run(&gen::case(&mut Rng::new(seed)));
+  }
+}
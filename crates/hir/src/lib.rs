@@ -17,7 +17,7 @@
 #![deny(missing_debug_implementations)]
 #![deny(rust_2018_idioms)]
 
-use la_arena::{Arena, Idx};
+use la_arena::{Arena, ArenaMap, Idx};
 use smol_str::SmolStr;
 use std::borrow::Borrow;
 use std::fmt;
@@ -28,6 +28,8 @@ pub use la_arena;
 pub struct Root {
   pub arenas: Arenas,
   pub items: Vec<ItemId>,
+  /// the doc comments of the items that have them
+  pub docs: ArenaMap<ItemId, String>,
 }
 
 #[derive(Debug, Default)]
@@ -127,7 +129,7 @@ pub enum Simp {
   Ambiguous(Name, Name),
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum IncDec {
   Inc,
   Dec,
@@ -154,7 +156,7 @@ pub struct Field {
   pub ty: TyId,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MathOp {
   Add,
   Sub,
@@ -168,7 +170,7 @@ pub enum MathOp {
   BitOr,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum BinOp {
   Math(MathOp),
   Eq,
@@ -181,7 +183,7 @@ pub enum BinOp {
   Or,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UnOp {
   Not,
   BitNot,
@@ -189,7 +191,7 @@ pub enum UnOp {
   Deref,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum AssignOp {
   Eq,
   OpEq(MathOp),
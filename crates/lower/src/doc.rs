@@ -0,0 +1,115 @@
+use syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+/// Returns the doc for an item, which is the block of comments immediately
+/// before it, with no blank lines in between, and then for a fn, its contract
+/// annotations, like `//@requires x > 0;`, in a code block.
+pub(crate) fn get(item: &SyntaxNode) -> Option<String> {
+  let comments = comments(item);
+  let contracts = contracts(item);
+  match (comments, contracts) {
+    (None, None) => None,
+    (Some(x), None) | (None, Some(x)) => Some(x),
+    (Some(comments), Some(contracts)) => {
+      Some(format!("{}\n\n{}", comments, contracts))
+    }
+  }
+}
+
+fn comments(item: &SyntaxNode) -> Option<String> {
+  let first = first_non_trivia(item)?;
+  let mut comments = Vec::new();
+  let mut tok = first.prev_token();
+  while let Some(t) = tok {
+    match t.kind() {
+      SyntaxKind::Whitespace => {
+        if t.text().matches('\n').count() > 1 {
+          break;
+        }
+      }
+      SyntaxKind::LineComment | SyntaxKind::BlockComment => {
+        // a comment trailing some other code on the same line is not a doc.
+        if !starts_line(&t) {
+          break;
+        }
+        comments.push(t.clone());
+      }
+      _ => break,
+    }
+    tok = t.prev_token();
+  }
+  let mut lines = Vec::new();
+  for comment in comments.iter().rev() {
+    let text = comment.text();
+    if comment.kind() == SyntaxKind::LineComment {
+      lines.push(strip_space(&text[2..]));
+    } else {
+      let text = text.trim_start_matches("/*").trim_start_matches('*');
+      let text = text.strip_suffix("*/").unwrap_or(text);
+      lines.extend(text.lines().map(|line| {
+        let line = line.trim_start();
+        strip_space(line.strip_prefix('*').unwrap_or(line))
+      }));
+    }
+  }
+  while lines.last() == Some(&"") {
+    lines.pop();
+  }
+  let start = lines.iter().position(|line| !line.is_empty())?;
+  Some(lines[start..].join("\n"))
+}
+
+/// Returns the annotations between the params of a fn and its body (or
+/// semicolon), which are the comments starting with `//@` or `/*@`.
+fn contracts(item: &SyntaxNode) -> Option<String> {
+  if item.kind() != SyntaxKind::FnItem {
+    return None;
+  }
+  let mut lines = Vec::new();
+  let mut tok = first_non_trivia(&item.last_child()?)?.prev_token();
+  while let Some(t) = tok {
+    match t.kind() {
+      SyntaxKind::Whitespace => {}
+      SyntaxKind::LineComment | SyntaxKind::BlockComment => {
+        let text = t.text();
+        if text.starts_with("//@") || text.starts_with("/*@") {
+          lines.push(text.trim_end().to_owned());
+        }
+      }
+      _ => break,
+    }
+    tok = t.prev_token();
+  }
+  if lines.is_empty() {
+    return None;
+  }
+  lines.reverse();
+  Some(format!("```c0\n{}\n```", lines.join("\n")))
+}
+
+fn first_non_trivia(node: &SyntaxNode) -> Option<SyntaxToken> {
+  node
+    .descendants_with_tokens()
+    .filter_map(|elem| elem.into_token())
+    .find(|tok| !is_trivia(tok.kind()))
+}
+
+fn is_trivia(kind: SyntaxKind) -> bool {
+  matches!(
+    kind,
+    SyntaxKind::Whitespace | SyntaxKind::LineComment | SyntaxKind::BlockComment
+  )
+}
+
+fn starts_line(tok: &SyntaxToken) -> bool {
+  match tok.prev_token() {
+    None => true,
+    Some(prev) => {
+      prev.kind() == SyntaxKind::Whitespace
+        && (prev.text().contains('\n') || prev.prev_token().is_none())
+    }
+  }
+}
+
+fn strip_space(s: &str) -> &str {
+  s.strip_prefix(' ').unwrap_or(s).trim_end()
+}
@@ -1,17 +1,16 @@
 use crate::ty::get as get_ty;
 use crate::util::Cx;
 use hir::{BinOp, MathOp, UnOp};
-use syntax::ast::{AstPtr, BinOpKind, Expr, UnOpKind};
+use syntax::ast::{BinOpKind, Expr, UnOpKind};
 use unwrap_or::unwrap_or;
 
 pub(crate) fn get(cx: &mut Cx, expr: Option<Expr>) -> hir::ExprId {
-  let (ptr, data) = expr.map_or((None, hir::Expr::None), |expr| {
-    (Some(AstPtr::new(&expr)), get_impl(cx, expr))
+  let (node, data) = expr.map_or((None, hir::Expr::None), |expr| {
+    (Some(expr.clone()), get_impl(cx, expr))
   });
   let ret = cx.arenas.expr.alloc(data);
-  if let Some(ptr) = ptr {
-    cx.ptrs.expr.insert(ptr.clone(), ret);
-    cx.ptrs.expr_back.insert(ret, ptr);
+  if let Some(node) = node {
+    cx.ptrs.expr.insert(&node, ret);
   }
   ret
 }
@@ -62,12 +61,14 @@ fn get_impl(cx: &mut Cx, expr: Expr) -> hir::Expr {
     }
     Expr::DerefFieldGetExpr(ref inner) => {
       let field = unwrap_or!(inner.ident(), return hir::Expr::None);
-      let ptr = AstPtr::new(&expr);
-      let expr = get(cx, inner.expr());
-      let deref = cx.arenas.expr.alloc(hir::Expr::UnOp(UnOp::Deref, expr));
-      // no entry for `cx.ptrs.expr`, since we'll have an entry from expr to the
-      // id of the FieldGet
-      cx.ptrs.expr_back.insert(deref, ptr);
+      let deref_expr = get(cx, inner.expr());
+      let deref = cx
+        .arenas
+        .expr
+        .alloc(hir::Expr::UnOp(UnOp::Deref, deref_expr));
+      // only a back entry, since we'll have an entry from expr to the id of
+      // the FieldGet
+      cx.ptrs.expr.insert_back(deref, &expr);
       hir::Expr::FieldGet(deref, field.text().into())
     }
     Expr::SubscriptExpr(expr) => {
@@ -1,15 +1,17 @@
+use crate::doc;
 use crate::stmt::get_block;
 use crate::ty::get as get_ty;
 use crate::util::{Cx, PragmaError};
 use hir::Name;
-use syntax::ast::{AstNode, AstPtr, FnTail, Item};
+use syntax::ast::{AstNode, FnTail, Item};
 
 pub(crate) fn get(
   cx: &mut Cx,
   pragma_ok: &mut bool,
   item: Item,
 ) -> Option<hir::ItemId> {
-  let ptr = AstPtr::new(&item);
+  let ast_item = item.clone();
+  let node = item.syntax().clone();
   let data = match item {
     Item::StructItem(item) => {
       let name: Name = item.ident()?.text().into();
@@ -60,7 +62,9 @@ pub(crate) fn get(
     }
   };
   let ret = cx.arenas.item.alloc(data);
-  cx.ptrs.item.insert(ptr.clone(), ret);
-  cx.ptrs.item_back.insert(ret, ptr);
+  cx.ptrs.item.insert(&ast_item, ret);
+  if let Some(doc) = doc::get(&node) {
+    cx.docs.insert(ret, doc);
+  }
   Some(ret)
 }
@@ -4,6 +4,7 @@
 #![deny(missing_docs)]
 #![deny(rust_2018_idioms)]
 
+mod doc;
 mod expr;
 mod item;
 mod root;
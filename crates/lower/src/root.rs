@@ -12,6 +12,7 @@ pub fn get(root: Root) -> Lowered {
       items.push(item)
     }
   }
+  cx.ptrs.finish();
   cx.arenas.item.shrink_to_fit();
   cx.arenas.expr.shrink_to_fit();
   cx.arenas.ty.shrink_to_fit();
@@ -21,6 +22,7 @@ pub fn get(root: Root) -> Lowered {
     root: hir::Root {
       arenas: cx.arenas,
       items,
+      docs: cx.docs,
     },
     ptrs: cx.ptrs,
     errors: cx.errors,
@@ -2,10 +2,10 @@ use crate::expr::get as get_expr;
 use crate::ty::get as get_ty;
 use crate::util::Cx;
 use hir::{AssignOp, IncDec, MathOp, Name};
-use syntax::ast::{AsgnOpKind, AstPtr, IncDecKind, Simp};
+use syntax::ast::{AsgnOpKind, IncDecKind, Simp};
 
 pub(crate) fn get(cx: &mut Cx, simp: Simp) -> Option<hir::SimpId> {
-  let ptr = AstPtr::new(&simp);
+  let node = simp.clone();
   let data = match simp {
     Simp::AsgnSimp(simp) => {
       let lhs = get_expr(cx, simp.lhs());
@@ -35,8 +35,7 @@ pub(crate) fn get(cx: &mut Cx, simp: Simp) -> Option<hir::SimpId> {
     }
   };
   let ret = cx.arenas.simp.alloc(data);
-  cx.ptrs.simp.insert(ptr.clone(), ret);
-  cx.ptrs.simp_back.insert(ret, ptr);
+  cx.ptrs.simp.insert(&node, ret);
   Some(ret)
 }
 
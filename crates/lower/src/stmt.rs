@@ -1,20 +1,19 @@
 use crate::expr::get as get_expr;
 use crate::simp::get as get_simp;
 use crate::util::Cx;
-use syntax::ast::{AstPtr, BlockStmt, Stmt};
+use syntax::ast::{BlockStmt, Stmt};
 
 pub(crate) fn get_block(cx: &mut Cx, stmt: BlockStmt) -> hir::StmtId {
   get(cx, Some(Stmt::BlockStmt(stmt)))
 }
 
 fn get(cx: &mut Cx, stmt: Option<Stmt>) -> hir::StmtId {
-  let (ptr, data) = stmt.map_or((None, hir::Stmt::None), |stmt| {
-    (Some(AstPtr::new(&stmt)), get_impl(cx, stmt))
+  let (node, data) = stmt.map_or((None, hir::Stmt::None), |stmt| {
+    (Some(stmt.clone()), get_impl(cx, stmt))
   });
   let ret = cx.arenas.stmt.alloc(data);
-  if let Some(ptr) = ptr {
-    cx.ptrs.stmt.insert(ptr.clone(), ret);
-    cx.ptrs.stmt_back.insert(ret, ptr);
+  if let Some(node) = node {
+    cx.ptrs.stmt.insert(&node, ret);
   }
   ret
 }
@@ -1,15 +1,14 @@
 use crate::util::Cx;
-use syntax::ast::{AstPtr, Ty};
+use syntax::ast::Ty;
 use unwrap_or::unwrap_or;
 
 pub(crate) fn get(cx: &mut Cx, ty: Option<Ty>) -> hir::TyId {
-  let (ptr, data) = ty.map_or((None, hir::Ty::None), |ty| {
-    (Some(AstPtr::new(&ty)), get_impl(cx, ty))
+  let (node, data) = ty.map_or((None, hir::Ty::None), |ty| {
+    (Some(ty.clone()), get_impl(cx, ty))
   });
   let ret = cx.arenas.ty.alloc(data);
-  if let Some(ptr) = ptr {
-    cx.ptrs.ty_back.insert(ret, ptr.clone());
-    cx.ptrs.ty.insert(ptr, ret);
+  if let Some(node) = node {
+    cx.ptrs.ty.insert(&node, ret);
   }
   ret
 }
@@ -1,10 +1,10 @@
 //! inspired by rust-analyzer
 
-use hir::la_arena::ArenaMap;
-use hir::{Arenas, ExprId, ItemId, SimpId, StmtId, TyId};
-use rustc_hash::FxHashMap;
+use hir::la_arena::{ArenaMap, Idx};
+use hir::{Arenas, ItemId};
 use std::fmt;
-use syntax::ast::{AstPtr, Expr, Item, Simp, Stmt, Ty};
+use std::ops::Index;
+use syntax::ast::{AstNode, AstPtr, Expr, Item, Simp, Stmt, Ty};
 use syntax::rowan::TextRange;
 
 /// Pointers between the AST and the HIR.
@@ -19,16 +19,112 @@ use syntax::rowan::TextRange;
 #[derive(Debug, Default)]
 #[allow(missing_docs)]
 pub struct Ptrs {
-  pub item: FxHashMap<AstPtr<Item>, ItemId>,
-  pub item_back: ArenaMap<ItemId, AstPtr<Item>>,
-  pub expr: FxHashMap<AstPtr<Expr>, ExprId>,
-  pub expr_back: ArenaMap<ExprId, AstPtr<Expr>>,
-  pub ty: FxHashMap<AstPtr<Ty>, TyId>,
-  pub ty_back: ArenaMap<TyId, AstPtr<Ty>>,
-  pub stmt: FxHashMap<AstPtr<Stmt>, StmtId>,
-  pub stmt_back: ArenaMap<StmtId, AstPtr<Stmt>>,
-  pub simp: FxHashMap<AstPtr<Simp>, SimpId>,
-  pub simp_back: ArenaMap<SimpId, AstPtr<Simp>>,
+  pub item: PtrMap<Item, hir::Item>,
+  pub expr: PtrMap<Expr, hir::Expr>,
+  pub ty: PtrMap<Ty, hir::Ty>,
+  pub stmt: PtrMap<Stmt, hir::Stmt>,
+  pub simp: PtrMap<Simp, hir::Simp>,
+}
+
+impl Ptrs {
+  /// Returns an estimate of the number of bytes these use on the heap.
+  pub fn heap_size(&self) -> usize {
+    self.item.heap_size()
+      + self.expr.heap_size()
+      + self.ty.heap_size()
+      + self.stmt.heap_size()
+      + self.simp.heap_size()
+  }
+
+  pub(crate) fn finish(&mut self) {
+    self.item.finish();
+    self.expr.finish();
+    self.ty.finish();
+    self.stmt.finish();
+    self.simp.finish();
+  }
+}
+
+/// Pointers between one kind of AST node and one kind of HIR node.
+///
+/// Both directions are dense: from HIR to AST is indexed by the HIR arena id,
+/// and from AST to HIR is a list sorted by the range of the AST node, so we can
+/// binary search it instead of hashing the whole `AstPtr`.
+pub struct PtrMap<N, T> {
+  /// sorted by range after lowering is done.
+  fwd: Vec<(TextRange, Idx<T>)>,
+  back: ArenaMap<Idx<T>, AstPtr<N>>,
+  /// one more than the greatest index in `back`.
+  back_len: usize,
+}
+
+impl<N: AstNode, T> PtrMap<N, T> {
+  /// Returns the HIR id for the AST node.
+  pub fn get(&self, node: &N) -> Option<Idx<T>> {
+    let range = node.syntax().text_range();
+    let key = |&(r, _): &(TextRange, Idx<T>)| (r.start(), r.end());
+    let start = self
+      .fwd
+      .partition_point(|x| key(x) < (range.start(), range.end()));
+    // there may be many nodes with the same range, e.g. a parenthesized
+    // expression and the expression it is in.
+    let ptr = AstPtr::new(node);
+    self.fwd[start..]
+      .iter()
+      .take_while(|&&(r, _)| r == range)
+      .find(|&&(_, idx)| self.back.get(idx) == Some(&ptr))
+      .map(|&(_, idx)| idx)
+  }
+
+  /// Returns an estimate of the number of bytes this uses on the heap.
+  pub fn heap_size(&self) -> usize {
+    self.fwd.capacity() * std::mem::size_of::<(TextRange, Idx<T>)>()
+      + self.back_len * std::mem::size_of::<Option<AstPtr<N>>>()
+  }
+
+  /// Adds pointers in both directions between the AST node and HIR id.
+  pub(crate) fn insert(&mut self, node: &N, idx: Idx<T>) {
+    self.fwd.push((node.syntax().text_range(), idx));
+    self.insert_back(idx, node);
+  }
+
+  /// Adds only a pointer from the HIR id to the AST node.
+  pub(crate) fn insert_back(&mut self, idx: Idx<T>, node: &N) {
+    self.back.insert(idx, AstPtr::new(node));
+    let len = u32::from(idx.into_raw()) as usize + 1;
+    self.back_len = self.back_len.max(len);
+  }
+
+  fn finish(&mut self) {
+    self.fwd.sort_by_key(|&(r, _)| (r.start(), r.end()));
+    self.fwd.shrink_to_fit();
+  }
+}
+
+impl<N, T> Index<Idx<T>> for PtrMap<N, T> {
+  type Output = AstPtr<N>;
+
+  fn index(&self, idx: Idx<T>) -> &AstPtr<N> {
+    &self.back[idx]
+  }
+}
+
+impl<N, T> Default for PtrMap<N, T> {
+  fn default() -> Self {
+    Self {
+      fwd: Vec::new(),
+      back: ArenaMap::default(),
+      back_len: 0,
+    }
+  }
+}
+
+impl<N, T> fmt::Debug for PtrMap<N, T> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("PtrMap")
+      .field("len", &self.fwd.len())
+      .finish_non_exhaustive()
+  }
 }
 
 /// An error for when a pragma appeared after some non-pragma item.
@@ -60,5 +156,6 @@ pub struct Lowered {
 pub(crate) struct Cx {
   pub(crate) ptrs: Ptrs,
   pub(crate) arenas: Arenas,
+  pub(crate) docs: ArenaMap<ItemId, String>,
   pub(crate) errors: Vec<PragmaError>,
 }
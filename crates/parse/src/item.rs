@@ -56,7 +56,10 @@ pub(crate) fn item(p: &mut Parser<'_>) {
   } else if let Some(exited) = ty_hd_opt(p) {
     fn_tail(p, exited);
   } else {
-    p.error(ErrorKind::Item)
+    // skip the token, else the caller would try to parse an item from it again
+    // and again.
+    p.error(ErrorKind::Item);
+    p.bump();
   }
 }
 
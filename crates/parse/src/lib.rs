@@ -13,7 +13,7 @@ mod ty;
 mod util;
 
 use std::fmt;
-use syntax::ast::{AstNode as _, Root};
+use syntax::ast::{AstNode as _, BlockStmt, Root};
 use syntax::token::Token;
 use syntax::SyntaxKind as SK;
 
@@ -26,6 +26,15 @@ pub struct Parse {
   pub errors: Vec<Error>,
 }
 
+/// The result of a parse of a single block statement.
+#[derive(Debug)]
+pub struct ParseBlock {
+  /// The block.
+  pub block: BlockStmt,
+  /// The errors encountered when parsing.
+  pub errors: Vec<Error>,
+}
+
 /// A parse error.
 pub type Error = event_parse::rowan_sink::Error<ErrorKind>;
 
@@ -75,3 +84,27 @@ pub fn get(tokens: &[Token<'_, SK>]) -> Parse {
     errors,
   }
 }
+
+/// Returns a parse of the tokens as a single block statement, or `None` if the
+/// tokens are not exactly one block statement, starting with `{` and ending
+/// with `}` (with no trivia before or after).
+///
+/// The ranges in the block and errors are relative to the start of the first
+/// token.
+pub fn get_block(tokens: &[Token<'_, SK>]) -> Option<ParseBlock> {
+  if tokens.first()?.kind != SK::LCurly || tokens.last()?.kind != SK::RCurly {
+    return None;
+  }
+  let mut p = Parser::new(tokens);
+  stmt::stmt_block(&mut p);
+  if p.peek().is_some() {
+    return None;
+  }
+  let mut sink = event_parse::rowan_sink::RowanSink::default();
+  p.finish(&mut sink);
+  let (block, errors) = sink.finish();
+  Some(ParseBlock {
+    block: BlockStmt::cast(block)?,
+    errors,
+  })
+}
@@ -0,0 +1,154 @@
+//! Conversion between URIs and paths.
+//!
+//! Files on disk have `file:` URIs. We convert those to and from paths only
+//! with `to_file_path` and `from_file_path`, which handle percent-encoding, so
+//! e.g. a path with spaces round-trips.
+//!
+//! Files only in the editor may have other schemes, like `untitled:` or
+//! `vscode-vfs:`. They have no path, but we still resolve relative paths
+//! against their URIs, so a `#use` in such a file finds the files next to it.
+
+#![deny(missing_debug_implementations)]
+#![deny(missing_docs)]
+#![deny(rust_2018_idioms)]
+
+use std::path::{self, Path, PathBuf};
+use uri_db::Uri;
+
+/// Returns the path of the file, if it is on disk.
+pub fn to_path(uri: &Uri) -> Option<PathBuf> {
+  if uri.scheme() == "file" {
+    uri.to_file_path().ok()
+  } else {
+    None
+  }
+}
+
+/// Returns the URI of the file at the absolute path.
+pub fn from_path(path: &Path) -> Option<Uri> {
+  Uri::from_file_path(path).ok()
+}
+
+/// Returns the URI of the directory at the absolute path.
+pub fn from_dir(path: &Path) -> Option<Uri> {
+  Uri::from_directory_path(path).ok()
+}
+
+/// Removes the `.` and `..` from the absolute path, without looking at the
+/// file system.
+pub fn normalize(path: &Path) -> PathBuf {
+  let mut ret = PathBuf::new();
+  for c in path.components() {
+    match c {
+      path::Component::CurDir => {}
+      path::Component::ParentDir => {
+        ret.pop();
+      }
+      c => ret.push(c),
+    }
+  }
+  ret
+}
+
+/// Returns the URI of the directory containing the file, for any scheme.
+///
+/// A URI with no hierarchy, like `untitled:Untitled-1`, is in the directory
+/// with an empty path, like `untitled:`.
+pub fn parent(uri: &Uri) -> Uri {
+  let mut ret = uri.clone();
+  if ret.cannot_be_a_base() {
+    ret.set_path("");
+  } else {
+    ret.path_segments_mut().expect("can be a base").pop();
+  }
+  ret.set_query(None);
+  ret.set_fragment(None);
+  ret
+}
+
+/// Returns the URI of the relative, `/`-separated `path` in the directory.
+pub fn join(dir: &Uri, path: &str) -> Result<Uri, JoinError> {
+  if path.starts_with('/') || Path::new(path).has_root() {
+    return Err(JoinError::Absolute);
+  }
+  let mut ret = dir.clone();
+  if ret.cannot_be_a_base() {
+    // there are no segments, so we build up the path ourselves.
+    let mut flat: Vec<_> = dir
+      .path()
+      .split('/')
+      .filter(|x| !x.is_empty())
+      .map(ToOwned::to_owned)
+      .collect();
+    for component in components(path) {
+      match component {
+        Component::Name(x) => flat.push(encode_segment(x)),
+        Component::Parent => {
+          flat.pop().ok_or(JoinError::AboveRoot)?;
+        }
+      }
+    }
+    ret.set_path(&flat.join("/"));
+    return Ok(ret);
+  }
+  // the number of segments we may pop.
+  let mut depth = dir
+    .path_segments()
+    .map_or(0, |xs| xs.filter(|x| !x.is_empty()).count());
+  let mut segments = ret.path_segments_mut().expect("can be a base");
+  segments.pop_if_empty();
+  for component in components(path) {
+    match component {
+      Component::Name(x) => {
+        depth += 1;
+        segments.push(x);
+      }
+      Component::Parent => {
+        depth = depth.checked_sub(1).ok_or(JoinError::AboveRoot)?;
+        segments.pop();
+      }
+    }
+  }
+  drop(segments);
+  Ok(ret)
+}
+
+/// Percent-encodes the path segment the same way as in a URI with segments.
+fn encode_segment(segment: &str) -> String {
+  let mut uri = Uri::parse("file:///").expect("valid URI");
+  uri
+    .path_segments_mut()
+    .expect("can be a base")
+    .push(segment);
+  uri.path()[1..].to_owned()
+}
+
+enum Component<'a> {
+  Name(&'a str),
+  Parent,
+}
+
+/// Returns the components of the relative path, skipping the `.`s.
+fn components(path: &str) -> impl Iterator<Item = Component<'_>> {
+  path.split('/').filter_map(|x| match x {
+    "" | "." => None,
+    ".." => Some(Component::Parent),
+    x => Some(Component::Name(x)),
+  })
+}
+
+/// An error when joining a path to a URI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinError {
+  /// The path was absolute.
+  Absolute,
+  /// The path went above the root with `..`.
+  AboveRoot,
+}
+
+/// Returns the extension of the file, like `c0`.
+pub fn extension(uri: &Uri) -> Option<&str> {
+  let name = uri.path().rsplit('/').next()?;
+  let (stem, ext) = name.rsplit_once('.')?;
+  (!stem.is_empty()).then_some(ext)
+}
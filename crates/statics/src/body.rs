@@ -0,0 +1,362 @@
+//! Checking fn bodies, reusing the results from the last time a file was
+//! checked for bodies that, along with everything they could see, are
+//! unchanged.
+//!
+//! The ids of the HIR in a body can change even if the body itself didn't, e.g.
+//! if an earlier body in the same file got bigger. So we remember the results
+//! for each node in the order we visit them, and apply them to whatever nodes
+//! are visited at the same point in the new body.
+
+use crate::stmt::get as get_stmt;
+use crate::util::error::{Error, ErrorKind};
+use crate::util::id::Id;
+use crate::util::ty::Ty;
+use crate::util::types::{Ambiguous, Cx, Env, FnCx};
+use hir::{Arenas, Expr, ExprId, ItemId, Simp, SimpId, Stmt, StmtId, TyId};
+use rustc_hash::{FxHashMap, FxHasher};
+use std::hash::{Hash, Hasher};
+
+/// The checked fn bodies of a file, to be passed to `get` every time that file
+/// is checked.
+///
+/// The `Cx` passed to `get` must be the same each time, since the results
+/// refer to its types.
+#[derive(Debug, Default)]
+pub struct Bodies {
+  cur: FxHashMap<ItemId, Body>,
+  prev: FxHashMap<ItemId, Body>,
+}
+
+impl Bodies {
+  pub(crate) fn start(&mut self) {
+    self.prev = std::mem::take(&mut self.cur);
+  }
+
+  pub(crate) fn finish(&mut self) {
+    self.prev.clear();
+  }
+}
+
+/// The results of checking a body. Each `Vec` is indexed by the order in which
+/// the nodes are visited in `Nodes`.
+#[derive(Debug)]
+struct Body {
+  key: u64,
+  expr_tys: Vec<Option<Ty>>,
+  ty_tys: Vec<Option<Ty>>,
+  ambiguous_simps: Vec<Option<Ambiguous>>,
+  errors: Vec<(RelId, ErrorKind)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RelId {
+  Expr(usize),
+  Ty(usize),
+  Stmt(usize),
+  Simp(usize),
+}
+
+/// Checks the body of the fn `item`, or re-uses the results from last time.
+pub(crate) fn get(
+  cx: &mut Cx,
+  env: &mut Env,
+  fn_cx: &mut FnCx<'_>,
+  bodies: &mut Bodies,
+  item: ItemId,
+  body: StmtId,
+  ret_ty: Ty,
+) {
+  let mut nodes = Nodes::default();
+  nodes.stmt(fn_cx.arenas, body);
+  let key = {
+    let mut h = FxHasher::default();
+    nodes.hasher.finish().hash(&mut h);
+    exports_hash(env).hash(&mut h);
+    let vars = fn_cx
+      .vars
+      .iter()
+      .map(|(name, data)| hash_one((name, data.ty, data.init)));
+    unordered(vars).hash(&mut h);
+    fn_cx.ret_ty.hash(&mut h);
+    ret_ty.hash(&mut h);
+    h.finish()
+  };
+  match bodies.prev.remove(&item) {
+    Some(old) if old.key == key => {
+      apply(cx, env, &nodes, &old);
+      bodies.cur.insert(item, old);
+    }
+    _ => {
+      let errors_start = cx.errors.len();
+      let diverges = get_stmt(cx, env, fn_cx, false, body);
+      if !diverges && ret_ty != Ty::Void {
+        cx.err(body, ErrorKind::FnMightNotReturnVal);
+      }
+      if let Some(new) = record(cx, env, &nodes, key, errors_start) {
+        bodies.cur.insert(item, new);
+      }
+    }
+  }
+}
+
+fn apply(cx: &mut Cx, env: &mut Env, nodes: &Nodes, body: &Body) {
+  for (&expr, &ty) in nodes.exprs.iter().zip(body.expr_tys.iter()) {
+    if let Some(ty) = ty {
+      env.expr_tys.insert(expr, ty);
+    }
+  }
+  for (&ty, &got) in nodes.tys.iter().zip(body.ty_tys.iter()) {
+    if let Some(got) = got {
+      env.ty_tys.insert(ty, got);
+    }
+  }
+  for (&simp, &a) in nodes.simps.iter().zip(body.ambiguous_simps.iter()) {
+    if let Some(a) = a {
+      env.ambiguous_simps.insert(simp, a);
+    }
+  }
+  for (id, kind) in body.errors.iter() {
+    let id = match *id {
+      RelId::Expr(i) => Id::Expr(nodes.exprs[i]),
+      RelId::Ty(i) => Id::Ty(nodes.tys[i]),
+      RelId::Stmt(i) => Id::Stmt(nodes.stmts[i]),
+      RelId::Simp(i) => Id::Simp(nodes.simps[i]),
+    };
+    cx.errors.push(Error {
+      id,
+      kind: kind.clone(),
+    });
+  }
+}
+
+/// Returns `None` if an error was reported for a node not in the body.
+fn record(
+  cx: &Cx,
+  env: &Env,
+  nodes: &Nodes,
+  key: u64,
+  errors_start: usize,
+) -> Option<Body> {
+  let errors = cx.errors[errors_start..]
+    .iter()
+    .map(|err| {
+      let id = match err.id {
+        Id::Expr(id) => RelId::Expr(position(&nodes.exprs, id)?),
+        Id::Ty(id) => RelId::Ty(position(&nodes.tys, id)?),
+        Id::Stmt(id) => RelId::Stmt(position(&nodes.stmts, id)?),
+        Id::Simp(id) => RelId::Simp(position(&nodes.simps, id)?),
+        Id::Item(_) => return None,
+      };
+      Some((id, err.kind.clone()))
+    })
+    .collect::<Option<Vec<_>>>()?;
+  Some(Body {
+    key,
+    expr_tys: nodes
+      .exprs
+      .iter()
+      .map(|&x| env.expr_tys.get(x).copied())
+      .collect(),
+    ty_tys: nodes
+      .tys
+      .iter()
+      .map(|&x| env.ty_tys.get(x).copied())
+      .collect(),
+    ambiguous_simps: nodes
+      .simps
+      .iter()
+      .map(|&x| env.ambiguous_simps.get(x).copied())
+      .collect(),
+    errors,
+  })
+}
+
+fn position<T: PartialEq>(xs: &[T], x: T) -> Option<usize> {
+  xs.iter().position(|y| *y == x)
+}
+
+/// Returns a hash of everything in the env that a body could depend on.
+fn exports_hash(env: &Env) -> u64 {
+  let fns = env
+    .fns
+    .iter()
+    .map(|(name, data)| hash_one((name, data.val())));
+  let structs = env.structs.iter().map(|(name, data)| {
+    let fields = data.val().iter().map(hash_one);
+    hash_one((name, unordered(fields)))
+  });
+  let type_defs = env
+    .type_defs
+    .iter()
+    .map(|(name, data)| hash_one((name, data.val())));
+  hash_one((unordered(fns), unordered(structs), unordered(type_defs)))
+}
+
+fn hash_one<T: Hash>(x: T) -> u64 {
+  let mut h = FxHasher::default();
+  x.hash(&mut h);
+  h.finish()
+}
+
+/// Combines hashes without regard for their order.
+fn unordered<I: Iterator<Item = u64>>(iter: I) -> u64 {
+  iter.fold(0, u64::wrapping_add)
+}
+
+/// The nodes in a body, in the order we visit them, and a hash of the shape of
+/// the body that doesn't depend on the ids of the nodes.
+#[derive(Default)]
+struct Nodes {
+  exprs: Vec<ExprId>,
+  tys: Vec<TyId>,
+  stmts: Vec<StmtId>,
+  simps: Vec<SimpId>,
+  hasher: FxHasher,
+}
+
+impl Nodes {
+  fn stmt(&mut self, arenas: &Arenas, stmt: StmtId) {
+    self.stmts.push(stmt);
+    let data = &arenas.stmt[stmt];
+    std::mem::discriminant(data).hash(&mut self.hasher);
+    match *data {
+      Stmt::None | Stmt::Break | Stmt::Continue => {}
+      Stmt::Simp(simp) => self.simp(arenas, simp),
+      Stmt::If(cond, yes, no) => {
+        self.expr(arenas, cond);
+        self.stmt(arenas, yes);
+        no.is_some().hash(&mut self.hasher);
+        if let Some(no) = no {
+          self.stmt(arenas, no);
+        }
+      }
+      Stmt::While(cond, body) => {
+        self.expr(arenas, cond);
+        self.stmt(arenas, body);
+      }
+      Stmt::For(init, cond, step, body) => {
+        init.is_some().hash(&mut self.hasher);
+        if let Some(init) = init {
+          self.simp(arenas, init);
+        }
+        self.expr(arenas, cond);
+        step.is_some().hash(&mut self.hasher);
+        if let Some(step) = step {
+          self.simp(arenas, step);
+        }
+        self.stmt(arenas, body);
+      }
+      Stmt::Return(expr) => {
+        expr.is_some().hash(&mut self.hasher);
+        if let Some(expr) = expr {
+          self.expr(arenas, expr);
+        }
+      }
+      Stmt::Block(ref stmts) => {
+        stmts.len().hash(&mut self.hasher);
+        for &stmt in stmts {
+          self.stmt(arenas, stmt);
+        }
+      }
+      Stmt::Assert(expr) | Stmt::Error(expr) => self.expr(arenas, expr),
+    }
+  }
+
+  fn simp(&mut self, arenas: &Arenas, simp: SimpId) {
+    self.simps.push(simp);
+    let data = &arenas.simp[simp];
+    std::mem::discriminant(data).hash(&mut self.hasher);
+    match *data {
+      Simp::Assign(lhs, op, rhs) => {
+        op.hash(&mut self.hasher);
+        self.expr(arenas, lhs);
+        self.expr(arenas, rhs);
+      }
+      Simp::IncDec(expr, inc_dec) => {
+        inc_dec.hash(&mut self.hasher);
+        self.expr(arenas, expr);
+      }
+      Simp::Decl(ref name, ty, expr) => {
+        name.hash(&mut self.hasher);
+        self.ty(arenas, ty);
+        expr.is_some().hash(&mut self.hasher);
+        if let Some(expr) = expr {
+          self.expr(arenas, expr);
+        }
+      }
+      Simp::Expr(expr) => self.expr(arenas, expr),
+      Simp::Ambiguous(ref lhs, ref rhs) => {
+        lhs.hash(&mut self.hasher);
+        rhs.hash(&mut self.hasher);
+      }
+    }
+  }
+
+  fn expr(&mut self, arenas: &Arenas, expr: ExprId) {
+    self.exprs.push(expr);
+    let data = &arenas.expr[expr];
+    std::mem::discriminant(data).hash(&mut self.hasher);
+    match *data {
+      Expr::None
+      | Expr::Int
+      | Expr::Bool
+      | Expr::Char
+      | Expr::String
+      | Expr::Null => {}
+      Expr::Name(ref name) => name.hash(&mut self.hasher),
+      Expr::BinOp(lhs, op, rhs) => {
+        op.hash(&mut self.hasher);
+        self.expr(arenas, lhs);
+        self.expr(arenas, rhs);
+      }
+      Expr::UnOp(op, expr) => {
+        op.hash(&mut self.hasher);
+        self.expr(arenas, expr);
+      }
+      Expr::Ternary(cond, yes, no) => {
+        self.expr(arenas, cond);
+        self.expr(arenas, yes);
+        self.expr(arenas, no);
+      }
+      Expr::Call(ref name, ref args) => {
+        name.hash(&mut self.hasher);
+        args.len().hash(&mut self.hasher);
+        for &arg in args {
+          self.expr(arenas, arg);
+        }
+      }
+      Expr::FieldGet(expr, ref name) => {
+        name.hash(&mut self.hasher);
+        self.expr(arenas, expr);
+      }
+      Expr::Subscript(array, idx) => {
+        self.expr(arenas, array);
+        self.expr(arenas, idx);
+      }
+      Expr::Alloc(ty) => self.ty(arenas, ty),
+      Expr::AllocArray(ty, len) => {
+        self.ty(arenas, ty);
+        self.expr(arenas, len);
+      }
+    }
+  }
+
+  fn ty(&mut self, arenas: &Arenas, ty: TyId) {
+    self.tys.push(ty);
+    let data = &arenas.ty[ty];
+    std::mem::discriminant(data).hash(&mut self.hasher);
+    match *data {
+      hir::Ty::None
+      | hir::Ty::Any
+      | hir::Ty::Int
+      | hir::Ty::Bool
+      | hir::Ty::Char
+      | hir::Ty::String
+      | hir::Ty::Void => {}
+      hir::Ty::Ptr(ty) | hir::Ty::Array(ty) => self.ty(arenas, ty),
+      hir::Ty::Struct(ref name) | hir::Ty::Name(ref name) => {
+        name.hash(&mut self.hasher)
+      }
+    }
+  }
+}
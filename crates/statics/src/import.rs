@@ -12,8 +12,11 @@ pub fn add_env(
 ) {
   for (name, sig) in env.fns.iter() {
     let mut sig = sig.clone();
-    if let Some(old_sig) = import.fns.get(name) {
-      let old_sig = old_sig.val();
+    if let Some(old) = import.fns.get(name) {
+      if sig.doc.is_none() {
+        sig.doc = old.doc.clone();
+      }
+      let old_sig = old.val();
       let want_len = old_sig.params.len();
       let got_len = sig.val().params.len();
       if want_len != got_len {
@@ -41,8 +44,8 @@ pub fn add_env(
       errors.push(ErrorKind::Duplicate(name.clone()));
     }
   }
-  for (name, &ty) in env.type_defs.iter() {
-    if import.type_defs.insert(name.clone(), ty).is_some() {
+  for (name, ty) in env.type_defs.iter() {
+    if import.type_defs.insert(name.clone(), ty.clone()).is_some() {
       errors.push(ErrorKind::Duplicate(name.clone()));
     }
   }
@@ -1,18 +1,21 @@
+use crate::body::{get as get_body, Bodies};
 use crate::ty::get as get_ty;
 use crate::util::error::ErrorKind;
 use crate::util::types::{
   Cx, Env, FnCx, FnSig, ItemData, NameToTy, Param, VarData,
 };
-use crate::util::{no_struct, no_unsized, no_void, ty::Ty, unify};
-use crate::{stmt::get as get_stmt, FileId};
+use crate::util::{no_struct, no_unsized, no_void, unify};
+use crate::FileId;
 use hir::{Arenas, Item, ItemId};
 
 pub(crate) fn get(
   arenas: &Arenas,
   cx: &mut Cx,
   env: &mut Env,
+  bodies: &mut Bodies,
   file: FileId,
   item: ItemId,
+  doc: Option<String>,
 ) {
   match arenas.item[item] {
     Item::Fn(ref name, ref params, ret_ty, body) => {
@@ -44,9 +47,11 @@ pub(crate) fn get(
         is_defined: body.is_some(),
         should_define: matches!(file, FileId::Source(_)),
       };
-      let old_sig = env.fns.get(name).map(ItemData::val);
+      let old = env.fns.get(name);
       let mut dup = env.type_defs.contains_key(name);
-      if let Some(old_sig) = old_sig {
+      // keep the docs from e.g. a declaration in a header.
+      let doc = doc.or_else(|| old.and_then(|x| x.doc.clone()));
+      if let Some(old_sig) = old.map(ItemData::val) {
         let want_len = old_sig.params.len();
         let got_len = sig.params.len();
         if want_len != got_len {
@@ -71,12 +76,11 @@ pub(crate) fn get(
         cx.err(item, ErrorKind::CannotDefnFn)
       }
       let ret_ty = sig.ret_ty;
-      env.fns.insert(name.clone(), ItemData::new(file, item, sig));
+      env
+        .fns
+        .insert(name.clone(), ItemData::new(file, item, doc, sig));
       if let Some(body) = body {
-        let diverges = get_stmt(cx, env, &mut fn_cx, false, body);
-        if !diverges && ret_ty != Ty::Void {
-          cx.err(body, ErrorKind::FnMightNotReturnVal);
-        }
+        get_body(cx, env, &mut fn_cx, bodies, item, body, ret_ty);
       }
     }
     Item::Struct(ref name, ref fields) => {
@@ -90,7 +94,7 @@ pub(crate) fn get(
       }
       if env
         .structs
-        .insert(name.clone(), ItemData::new(file, item, sig))
+        .insert(name.clone(), ItemData::new(file, item, doc, sig))
         .is_some()
       {
         cx.err(item, ErrorKind::Duplicate(name.clone()))
@@ -101,7 +105,7 @@ pub(crate) fn get(
       no_void(cx, got_ty, ty);
       let dup = env
         .type_defs
-        .insert(name.clone(), ItemData::new(file, item, got_ty))
+        .insert(name.clone(), ItemData::new(file, item, doc, got_ty))
         .is_some()
         || env.fns.contains_key(name);
       if dup {
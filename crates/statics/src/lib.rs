@@ -3,6 +3,7 @@
 #![deny(missing_debug_implementations)]
 #![deny(rust_2018_idioms)]
 
+mod body;
 mod expr;
 mod import;
 mod item;
@@ -12,11 +13,13 @@ mod stmt;
 mod ty;
 mod util;
 
+pub use body::Bodies;
 pub use import::add_env;
 pub use root::get;
 pub use util::error::{Error, ErrorKind};
 pub use util::id::Id;
 pub use util::ty::{Ty, TyData, TyDb};
 pub use util::types::{
-  Cx, Env, ExprTys, FileId, FnSig, ItemData, NameToTy, Param,
+  Ambiguous, AmbiguousSimps, Cx, Env, ExprTys, FileId, FnSig, ItemData,
+  NameToTy, Param,
 };
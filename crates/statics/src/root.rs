@@ -1,10 +1,20 @@
+use crate::body::Bodies;
 use crate::item::get as get_item;
 use crate::util::types::{Cx, Env, FileId};
 use hir::Root;
 
-pub fn get(cx: &mut Cx, mut env: Env, file: FileId, root: &Root) -> Env {
+pub fn get(
+  cx: &mut Cx,
+  mut env: Env,
+  bodies: &mut Bodies,
+  file: FileId,
+  root: &Root,
+) -> Env {
+  bodies.start();
   for &item in root.items.iter() {
-    get_item(&root.arenas, cx, &mut env, file, item);
+    let doc = root.docs.get(item).cloned();
+    get_item(&root.arenas, cx, &mut env, bodies, file, item, doc);
   }
+  bodies.finish();
   env
 }
@@ -2,7 +2,7 @@ use crate::expr::{get as get_expr, get_name as get_name_expr};
 use crate::ty::get as get_ty;
 use crate::util::error::ErrorKind;
 use crate::util::ty::{Ty, TyData};
-use crate::util::types::{Cx, Env, FnCx, VarData};
+use crate::util::types::{Ambiguous, Cx, Env, FnCx, VarData};
 use crate::util::{no_struct, no_void, unify};
 use hir::{Arenas, AssignOp, Expr, ExprId, Name, Simp, SimpId, UnOp};
 
@@ -86,12 +86,14 @@ pub(crate) fn get<'a>(
           let rhs_ty = get_name_expr(cx, &fn_cx.vars, simp, rhs);
           unify(cx, Ty::Int, lhs_ty, simp);
           unify(cx, Ty::Int, rhs_ty, simp);
+          env.ambiguous_simps.insert(simp, Ambiguous::Mul);
         }
         // declaration. largely duplicated from Simp::Decl.
         Some(ty) => {
           let ty = cx.tys.mk(TyData::Ptr(*ty.val()));
           no_void(cx, ty, simp);
           no_struct(cx, ty, simp);
+          env.ambiguous_simps.insert(simp, Ambiguous::Decl(ty));
           let data = VarData { ty, init: false };
           let dup = fn_cx.vars.insert(rhs.clone(), data).is_some()
             || env.type_defs.contains_key(rhs);
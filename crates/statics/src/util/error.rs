@@ -3,13 +3,13 @@ use crate::util::ty::{Ty, TyDb};
 use hir::Name;
 use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Error {
   pub id: Id,
   pub kind: ErrorKind,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ErrorKind {
   CallNonFnTy(Ty),
   CannotAssign,
@@ -1,7 +1,7 @@
 use crate::util::error::{Error, ErrorKind};
 use crate::util::id::Id;
 use crate::util::ty::{Ty, TyDb};
-use hir::{la_arena::ArenaMap, Arenas, ExprId, ItemId, Name, TyId};
+use hir::{la_arena::ArenaMap, Arenas, ExprId, ItemId, Name, SimpId, TyId};
 use rustc_hash::FxHashMap;
 use std::fmt;
 use uri_db::UriId;
@@ -13,25 +13,35 @@ pub enum FileId {
   Header(UriId),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ItemData<T> {
   id: Option<(UriId, ItemId)>,
+  pub(crate) doc: Option<String>,
   val: T,
 }
 
 impl<T> ItemData<T> {
-  pub(crate) fn new(file: FileId, item: ItemId, val: T) -> Self {
+  pub(crate) fn new(
+    file: FileId,
+    item: ItemId,
+    doc: Option<String>,
+    val: T,
+  ) -> Self {
     let id = match file {
       FileId::StdLib => None,
       FileId::Source(uri) | FileId::Header(uri) => Some((uri, item)),
     };
-    Self { id, val }
+    Self { id, doc, val }
   }
 
   pub fn id(&self) -> Option<(UriId, ItemId)> {
     self.id
   }
 
+  pub fn doc(&self) -> Option<&str> {
+    self.doc.as_deref()
+  }
+
   pub fn val(&self) -> &T {
     &self.val
   }
@@ -41,7 +51,7 @@ impl<T> ItemData<T> {
   }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FnSig {
   pub params: Vec<Param>,
   pub ret_ty: Ty,
@@ -85,7 +95,7 @@ impl fmt::Display for FnSigDisplay<'_> {
 
 pub type NameToTy = FxHashMap<Name, Ty>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Param {
   /// only used for informational messages
   pub name: Name,
@@ -116,6 +126,19 @@ pub type ExprTys = ArenaMap<ExprId, Ty>;
 /// scope
 pub type TyTys = ArenaMap<TyId, Ty>;
 
+/// how each `hir::Simp::Ambiguous` was resolved, which depends on what typedefs
+/// were in scope when it was checked
+pub type AmbiguousSimps = ArenaMap<SimpId, Ambiguous>;
+
+/// the resolution of an ambiguous simp like `a * b;`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ambiguous {
+  /// `a` and `b` are variables, and this is a multiplication.
+  Mul,
+  /// `a` is a typedef, and this declares `b` with the given type.
+  Decl(Ty),
+}
+
 #[derive(Debug, Default)]
 pub struct Env {
   pub fns: FxHashMap<Name, ItemData<FnSig>>,
@@ -123,6 +146,7 @@ pub struct Env {
   pub type_defs: FxHashMap<Name, ItemData<Ty>>,
   pub expr_tys: ExprTys,
   pub ty_tys: TyTys,
+  pub ambiguous_simps: AmbiguousSimps,
 }
 
 impl Env {
@@ -132,6 +156,7 @@ impl Env {
       "main".into(),
       ItemData {
         id: None,
+        doc: None,
         val: FnSig {
           params: vec![],
           ret_ty: Ty::Int,
@@ -142,6 +167,15 @@ impl Env {
     );
     ret
   }
+
+  /// Returns whether this and the other env have the same fns, structs, and
+  /// type defs, i.e. whether a file that imports one would see the same thing
+  /// if it imported the other instead.
+  pub fn same_exports(&self, other: &Self) -> bool {
+    self.fns == other.fns
+      && self.structs == other.structs
+      && self.type_defs == other.type_defs
+  }
 }
 
 #[derive(Debug, Default)]
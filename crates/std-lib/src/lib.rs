@@ -4,35 +4,36 @@
 #![deny(missing_docs)]
 #![deny(rust_2018_idioms)]
 
-use statics::{Cx, Env, FileId};
+use statics::{Bodies, Cx, Env, FileId};
+use std::cell::RefCell;
+use std::rc::Rc;
 use std::str::FromStr;
 
 /// The standard libraries.
-#[derive(Debug)]
+///
+/// The environment of each library is computed only when it is first asked
+/// for, since most programs use only a few libraries, if any.
+#[derive(Debug, Default)]
 pub struct StdLib {
-  args: Env,
-  conio: Env,
-  file: Env,
-  img: Env,
-  parse: Env,
-  rand: Env,
-  string: Env,
-  util: Env,
+  envs: [RefCell<Option<Rc<Env>>>; 8],
 }
 
 impl StdLib {
-  /// Returns the environment of the given library.
-  pub fn get(&self, lib: Lib) -> &Env {
-    match lib {
-      Lib::Args => &self.args,
-      Lib::Conio => &self.conio,
-      Lib::File => &self.file,
-      Lib::Img => &self.img,
-      Lib::Parse => &self.parse,
-      Lib::Rand => &self.rand,
-      Lib::String => &self.string,
-      Lib::Util => &self.util,
-    }
+  /// Returns the environment of the given library, computing it if this is the
+  /// first time it was asked for.
+  ///
+  /// The same [`Cx`] must be passed every time, since the returned `Env`s
+  /// refer to the types in its `TyDb`.
+  pub fn get(&self, cx: &mut Cx, lib: Lib) -> Rc<Env> {
+    self.envs[lib as usize]
+      .borrow_mut()
+      .get_or_insert_with(|| Rc::new(get_one(cx, lib.contents())))
+      .clone()
+  }
+
+  /// Returns the environment of the given library, if it was already computed.
+  pub fn get_computed(&self, lib: Lib) -> Option<Rc<Env>> {
+    self.envs[lib as usize].borrow().clone()
   }
 }
 
@@ -50,6 +51,21 @@ pub enum Lib {
   Util,
 }
 
+impl Lib {
+  fn contents(self) -> &'static str {
+    match self {
+      Lib::Args => include_str!("data/args.h0"),
+      Lib::Conio => include_str!("data/conio.h0"),
+      Lib::File => include_str!("data/file.h0"),
+      Lib::Img => include_str!("data/img.h0"),
+      Lib::Parse => include_str!("data/parse.h0"),
+      Lib::Rand => include_str!("data/rand.h0"),
+      Lib::String => include_str!("data/string.h0"),
+      Lib::Util => include_str!("data/util.h0"),
+    }
+  }
+}
+
 impl FromStr for Lib {
   type Err = ();
   fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -68,37 +84,52 @@ impl FromStr for Lib {
   }
 }
 
-/// The [`Cx`] will always have no errors. The `TyDb` inside the `Cx` allows
-/// getting information about the returned `Env`s inside the [`StdLib`].
-pub fn get() -> (Cx, StdLib) {
-  let mut cx = Cx::default();
-  let std_lib = StdLib {
-    args: get_one(&mut cx, include_str!("data/args.h0")),
-    conio: get_one(&mut cx, include_str!("data/conio.h0")),
-    file: get_one(&mut cx, include_str!("data/file.h0")),
-    img: get_one(&mut cx, include_str!("data/img.h0")),
-    parse: get_one(&mut cx, include_str!("data/parse.h0")),
-    rand: get_one(&mut cx, include_str!("data/rand.h0")),
-    string: get_one(&mut cx, include_str!("data/string.h0")),
-    util: get_one(&mut cx, include_str!("data/util.h0")),
-  };
-  (cx, std_lib)
-}
-
+/// Adds no errors to the `Cx`.
 fn get_one(cx: &mut Cx, contents: &str) -> Env {
+  let errors_start = cx.errors.len();
   let lexed = lex::get(contents);
   let parsed = parse::get(&lexed.tokens);
   let lowered = lower::get(parsed.root);
-  let ret = statics::get(cx, Env::default(), FileId::StdLib, &lowered.root);
+  let ret = statics::get(
+    cx,
+    Env::default(),
+    &mut Bodies::default(),
+    FileId::StdLib,
+    &lowered.root,
+  );
   assert!(lexed.errors.is_empty());
   assert!(parsed.errors.is_empty());
   assert!(lowered.errors.is_empty());
-  assert!(cx.errors.is_empty());
+  assert_eq!(cx.errors.len(), errors_start);
   ret
 }
 
 #[test]
 fn t() {
-  let (cx, _) = get();
-  assert!(cx.errors.is_empty());
+  let mut cx = Cx::default();
+  let std_lib = StdLib::default();
+  let libs = [
+    Lib::Args,
+    Lib::Conio,
+    Lib::File,
+    Lib::Img,
+    Lib::Parse,
+    Lib::Rand,
+    Lib::String,
+    Lib::Util,
+  ];
+  for lib in libs {
+    assert!(std_lib.get_computed(lib).is_none());
+    let env = std_lib.get(&mut cx, lib);
+    assert!(Rc::ptr_eq(&env, &std_lib.get_computed(lib).unwrap()));
+    assert!(env.fns.values().all(|x| x.doc().is_some()));
+    assert!(env.structs.values().all(|x| x.doc().is_some()));
+    assert!(env.type_defs.values().all(|x| x.doc().is_some()));
+  }
+  // the contracts are part of the docs.
+  let string = std_lib.get(&mut cx, Lib::String);
+  let doc = string.fns.get("string_sub").unwrap().doc().unwrap();
+  assert!(doc.contains(
+    "//@requires 0 <= start && start <= end && end <= string_length(a);"
+  ));
 }
@@ -4,11 +4,11 @@
 #![deny(missing_docs)]
 #![deny(rust_2018_idioms)]
 
+use paths::JoinError;
 use std::fmt;
-use std::path::{Component, Path, PathBuf};
 use std_lib::Lib;
 use syntax::rowan::TextRange;
-use uri_db::{UriDb, UriId};
+use uri_db::{Uri, UriDb, UriId};
 
 /// The processed use pragmas.
 #[derive(Debug, Default)]
@@ -21,11 +21,21 @@ pub struct Uses {
 
 /// Translates the `uses` for the file at the given `id` into fully resolved
 /// uses.
-pub fn get(uris: &UriDb, id: UriId, uses: Vec<syntax::Use>) -> Uses {
+///
+/// A local use is resolved relative to the directory of the file first, then
+/// relative to each of the `include_dirs` in order, like `cc0 -L`. The file
+/// need not be on disk: e.g. a use in `untitled:a.c0` may refer to
+/// `untitled:b.h0`.
+pub fn get(
+  uris: &UriDb,
+  id: UriId,
+  uses: Vec<syntax::Use>,
+  include_dirs: &[Uri],
+) -> Uses {
   let mut ret = Uses::default();
   for u in uses {
     let range = u.range;
-    match get_one(uris, id, &u.path, u.kind) {
+    match get_one(uris, id, &u.path, u.kind, include_dirs) {
       Ok(kind) => ret.uses.push(Use { kind, range }),
       Err(kind) => ret.errors.push(Error { kind, range }),
     }
@@ -38,31 +48,18 @@ fn get_one(
   id: UriId,
   path: &str,
   kind: syntax::UseKind,
+  include_dirs: &[Uri],
 ) -> Result<UseKind, ErrorKind> {
   match kind {
     syntax::UseKind::Local => {
-      let uri = &uris[id];
-      let mut buf = PathBuf::from(uri.path()).parent().unwrap().to_owned();
-      for c in Path::new(path).components() {
-        match c {
-          Component::Prefix(_) | Component::RootDir => {
-            return Err(ErrorKind::AbsolutePath)
-          }
-          Component::CurDir => {}
-          Component::ParentDir => {
-            if !buf.pop() {
-              return Err(ErrorKind::NoSuchPath);
-            }
-          }
-          Component::Normal(s) => buf.push(s),
+      let mut ret = resolve(uris, &paths::parent(&uris[id]), path);
+      for inc in include_dirs {
+        if !matches!(ret, Err(ErrorKind::NoSuchPath)) {
+          break;
         }
+        ret = resolve(uris, inc, path);
       }
-      let mut new_uri = uri.clone();
-      new_uri.set_path(buf.as_os_str().to_string_lossy().as_ref());
-      match uris.get_id(&new_uri) {
-        Some(x) => Ok(UseKind::File(x)),
-        None => Err(ErrorKind::NoSuchPath),
-      }
+      ret
     }
     syntax::UseKind::Lib => match path.parse::<Lib>() {
       Ok(lib) => Ok(UseKind::Lib(lib)),
@@ -71,6 +68,18 @@ fn get_one(
   }
 }
 
+/// Resolves the relative `path` against the directory `dir`.
+fn resolve(uris: &UriDb, dir: &Uri, path: &str) -> Result<UseKind, ErrorKind> {
+  let uri = paths::join(dir, path).map_err(|e| match e {
+    JoinError::Absolute => ErrorKind::AbsolutePath,
+    JoinError::AboveRoot => ErrorKind::NoSuchPath,
+  })?;
+  match uris.get_id(&uri) {
+    Some(x) => Ok(UseKind::File(x)),
+    None => Err(ErrorKind::NoSuchPath),
+  }
+}
+
 /// A use.
 #[derive(Debug)]
 pub struct Use {
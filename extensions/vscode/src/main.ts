@@ -1,14 +1,112 @@
-import { ExtensionContext, workspace } from "vscode";
 import {
+  commands,
+  EventEmitter,
+  ExtensionContext,
+  StatusBarAlignment,
+  StatusBarItem,
+  TextDocumentContentProvider,
+  Uri,
+  ViewColumn,
+  window,
+  workspace,
+} from "vscode";
+import {
+  ClientCapabilities,
   LanguageClient,
   LanguageClientOptions,
   ServerOptions,
+  StaticFeature,
 } from "vscode-languageclient/node";
 import { join } from "path";
 
-// this does not support many roots
+// one server handles every workspace folder, so there is only one client
 let client: LanguageClient | null = null;
 
+// what the server is up to, from its `c0ls/status` notifications.
+interface StatusParams {
+  state: "idle" | "analyzing" | "error";
+  message?: string;
+}
+
+// tells the server we want `c0ls/status` notifications.
+class StatusFeature implements StaticFeature {
+  fillClientCapabilities(capabilities: ClientCapabilities): void {
+    capabilities.experimental = {
+      ...capabilities.experimental,
+      statusNotification: true,
+    };
+  }
+
+  initialize(): void {}
+
+  dispose(): void {}
+}
+
+function showStatus(item: StatusBarItem, params: StatusParams) {
+  switch (params.state) {
+    case "idle":
+      item.text = "$(check) C0";
+      break;
+    case "analyzing":
+      item.text = "$(sync~spin) C0";
+      break;
+    case "error":
+      item.text = "$(error) C0";
+      break;
+  }
+  item.tooltip = params.message ?? `c0ls is ${params.state}`;
+}
+
+// the scheme of the read-only documents showing what the server makes of a
+// document. the path is the request to make, and the query is the URI of the
+// document.
+const inspectScheme = "c0ls-inspect";
+
+// the commands that show such a document, and their requests.
+const inspectCommands = [
+  { command: "c0ls.syntaxTree", method: "c0ls/syntaxTree" },
+  { command: "c0ls.hir", method: "c0ls/hir" },
+  { command: "c0ls.tokens", method: "c0ls/tokens" },
+];
+
+class InspectProvider implements TextDocumentContentProvider {
+  private readonly changed = new EventEmitter<Uri>();
+  readonly onDidChange = this.changed.event;
+
+  constructor(private readonly client: LanguageClient) {}
+
+  async provideTextDocumentContent(uri: Uri): Promise<string> {
+    await this.client.onReady();
+    const params = { textDocument: { uri: uri.query } };
+    const ret = await this.client.sendRequest<string | null>(uri.path, params);
+    return ret ?? "c0ls doesn't know about this document";
+  }
+
+  // asks again for the contents, which may be out of date.
+  refresh(uri: Uri) {
+    this.changed.fire(uri);
+  }
+}
+
+async function inspect(provider: InspectProvider, method: string) {
+  const editor = window.activeTextEditor;
+  if (editor === undefined || editor.document.languageId !== "c0") {
+    window.showErrorMessage("Open a C0 file first.");
+    return;
+  }
+  const uri = Uri.from({
+    scheme: inspectScheme,
+    path: method,
+    query: editor.document.uri.toString(),
+  });
+  provider.refresh(uri);
+  const doc = await workspace.openTextDocument(uri);
+  await window.showTextDocument(doc, {
+    viewColumn: ViewColumn.Beside,
+    preserveFocus: true,
+  });
+}
+
 export function activate(cx: ExtensionContext) {
   if (client !== null) {
     return;
@@ -17,13 +115,36 @@ export function activate(cx: ExtensionContext) {
     command: cx.asAbsolutePath(join("out", "c0ls")),
   };
   const clientOpts: LanguageClientOptions = {
-    documentSelector: [{ scheme: "file", language: "c0" }],
-    synchronize: {
-      fileEvents: workspace.createFileSystemWatcher("**/*.{c0,h0}"),
-    },
+    documentSelector: [
+      { scheme: "file", language: "c0" },
+      { scheme: "untitled", language: "c0" },
+      { scheme: "vscode-vfs", language: "c0" },
+    ],
+    // the server asks for the settings again when they change.
+    initializationOptions: workspace.getConfiguration("c0ls"),
   };
-  client = new LanguageClient("c0ls", serverOpts, clientOpts, true);
-  cx.subscriptions.push(client.start());
+  const c = new LanguageClient("c0ls", serverOpts, clientOpts, true);
+  client = c;
+  c.registerFeature(new StatusFeature());
+  const item = window.createStatusBarItem(StatusBarAlignment.Left);
+  showStatus(item, { state: "analyzing" });
+  item.show();
+  cx.subscriptions.push(item);
+  c.onReady().then(() => {
+    c.onNotification("c0ls/status", (params: StatusParams) =>
+      showStatus(item, params)
+    );
+  });
+  const provider = new InspectProvider(c);
+  cx.subscriptions.push(
+    workspace.registerTextDocumentContentProvider(inspectScheme, provider)
+  );
+  for (const { command, method } of inspectCommands) {
+    cx.subscriptions.push(
+      commands.registerCommand(command, () => inspect(provider, method))
+    );
+  }
+  cx.subscriptions.push(c.start());
 }
 
 export function deactivate(): Promise<void> {
@@ -58,6 +58,14 @@ fn main() -> Result<()> {
       cmd!(sh, "cargo clippy").run()?;
       cmd!(sh, "cargo test").run()?;
     }
+    "bench" => {
+      let args = args.finish();
+      cmd!(sh, "cargo run --release -p bench -- {args...}").run()?;
+    }
+    "fuzz" => {
+      let args = args.finish();
+      cmd!(sh, "cargo run --release -p fuzz -- {args...}").run()?;
+    }
     "ck-test-data" => {
       finish_args(args)?;
       ck_test_data(&sh)?